@@ -0,0 +1,127 @@
+//! Compares the different storage layouts of the holders.
+//!
+//! Run using `cargo bench`, or `cargo bench --features fxhash,ahash` to also compare the hashers.
+extern crate crow_util;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crow_util::holder::{ArenaHolder, Holder};
+#[cfg(feature = "ahash")]
+use crow_util::holder::AHolder;
+#[cfg(feature = "fxhash")]
+use crow_util::holder::FxHolder;
+
+const ELEMENTS: usize = 100_000;
+const RUNS: u32 = 10;
+
+#[derive(Clone, Copy)]
+struct Sprite {
+    _x: f32,
+    _y: f32,
+    _texture: u32,
+}
+
+fn sprite(i: usize) -> Sprite {
+    Sprite {
+        _x: i as f32,
+        _y: i as f32 * 0.5,
+        _texture: i as u32,
+    }
+}
+
+fn bench<F: FnMut()>(name: &str, mut f: F) {
+    let mut total = Duration::new(0, 0);
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        f();
+        total += start.elapsed();
+    }
+    println!("{:<24} {:>10.3?} per run", name, total / RUNS);
+}
+
+fn main() {
+    let keys: Vec<String> = (0..ELEMENTS).map(|i| format!("sprite_{}", i)).collect();
+
+    bench("Holder insert", || {
+        let holder = Holder::new();
+        for (i, key) in keys.iter().enumerate() {
+            holder.insert(key, sprite(i));
+        }
+        black_box(&holder);
+    });
+
+    bench("ArenaHolder insert", || {
+        let holder = ArenaHolder::new();
+        for (i, key) in keys.iter().enumerate() {
+            holder.insert(key, sprite(i));
+        }
+        black_box(&holder);
+    });
+
+    let holder = Holder::new();
+    let arena = ArenaHolder::new();
+    for (i, key) in keys.iter().enumerate() {
+        holder.insert(key, sprite(i));
+        arena.insert(key, sprite(i));
+    }
+
+    bench("Holder get", || {
+        for key in &keys {
+            black_box(holder.get(key));
+        }
+    });
+
+    bench("ArenaHolder get", || {
+        for key in &keys {
+            black_box(arena.get(key));
+        }
+    });
+
+    let ids: Vec<_> = keys.iter().map(|key| holder.id(key).unwrap()).collect();
+    bench("Holder get_by_id", || {
+        for &id in &ids {
+            black_box(holder.get_by_id(id));
+        }
+    });
+
+    // cloning would allocate the keys in a random order, making the lookups slower than those of `holder`.
+    let frozen = Holder::new();
+    for (i, key) in keys.iter().enumerate() {
+        frozen.insert(key, sprite(i));
+    }
+    let frozen = frozen.freeze();
+    bench("FrozenHolder get", || {
+        for key in &keys {
+            black_box(frozen.get(key));
+        }
+    });
+
+    #[cfg(feature = "fxhash")]
+    {
+        let fx = FxHolder::default();
+        for (i, key) in keys.iter().enumerate() {
+            fx.insert(key, sprite(i));
+        }
+
+        bench("FxHolder get", || {
+            for key in &keys {
+                black_box(fx.get(key));
+            }
+        });
+    }
+
+    #[cfg(feature = "ahash")]
+    {
+        let a = AHolder::default();
+        for (i, key) in keys.iter().enumerate() {
+            a.insert(key, sprite(i));
+        }
+
+        bench("AHolder get", || {
+            for key in &keys {
+                black_box(a.get(key));
+            }
+        });
+    }
+}
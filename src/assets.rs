@@ -0,0 +1,552 @@
+//! A module containing an asset manager, which loads files from disk and stores them in a holder.
+//!
+//! For examples and further explanation, visit [`AssetManager`](struct.AssetManager.html).
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use holder::AnyHolder;
+
+use self::watch::Watcher;
+
+mod watch;
+
+/// Decodes the content of a file into an asset of type `Asset`.
+///
+/// Loaders are registered at an [`AssetManager`](struct.AssetManager.html), which chooses the loader by the extension of the file.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::assets::Loader;
+/// use std::string::FromUtf8Error;
+///
+/// struct TextLoader;
+///
+/// impl Loader for TextLoader {
+///     type Asset = String;
+///     type Error = FromUtf8Error;
+///
+///     fn extensions(&self) -> &[&str] {
+///         &["txt", "md"]
+///     }
+///
+///     fn load(&self, bytes: &[u8]) -> Result<String, FromUtf8Error> {
+///         String::from_utf8(bytes.to_vec())
+///     }
+/// }
+///
+/// assert_eq!(TextLoader.load(b"hello"), Ok("hello".to_string()));
+/// ```
+pub trait Loader {
+    /// The type of the loaded assets.
+    type Asset;
+    /// The error returned in case the content of a file is invalid.
+    type Error: Error + 'static;
+
+    /// Returns the file extensions supported by this loader, without the leading dot.
+    ///
+    /// Extensions are compared case-insensitively.
+    fn extensions(&self) -> &[&str];
+
+    /// Decodes the content of a file.
+    fn load(&self, bytes: &[u8]) -> Result<Self::Asset, Self::Error>;
+}
+
+/// A loader whose concrete type was erased, only the type of its assets is known.
+type LoadFn<T> = Rc<dyn Fn(&[u8]) -> Result<T, Box<dyn Error>>>;
+
+/// Loads assets from a root directory and caches them, which allows for immutable access while still allowing new assets to be loaded.
+///
+/// Each asset is identified by its type together with its normalized path relative to the root directory,
+/// so `"textures/player.png"` and `"./textures//player.png"` refer to the same asset.
+/// Paths which would leave the root directory are rejected.
+/// Every asset type has its own [`Holder<T>`](../holder/struct.Holder.html), so references to assets stay valid while new assets are loaded.
+///
+/// During development, changed files can be reloaded without restarting the game, see [`set_watching`](#method.set_watching)
+/// and [`poll_changes`](#method.poll_changes).
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::assets::{AssetManager, Loader};
+/// use std::fs;
+/// use std::process;
+/// use std::string::FromUtf8Error;
+///
+/// struct TextLoader;
+///
+/// impl Loader for TextLoader {
+///     type Asset = String;
+///     type Error = FromUtf8Error;
+///
+///     fn extensions(&self) -> &[&str] {
+///         &["txt"]
+///     }
+///
+///     fn load(&self, bytes: &[u8]) -> Result<String, FromUtf8Error> {
+///         String::from_utf8(bytes.to_vec())
+///     }
+/// }
+///
+/// let root = std::env::temp_dir().join(format!("crow_util_assets_manager_{}", process::id()));
+/// fs::create_dir_all(root.join("dialog")).unwrap();
+/// fs::write(root.join("dialog/intro.txt"), "Welcome!").unwrap();
+///
+/// let mut assets = AssetManager::new(&root);
+/// assets.add_loader(TextLoader);
+///
+/// let intro: &String = assets.load("dialog/intro.txt").unwrap();
+/// assert_eq!(intro, "Welcome!");
+///
+/// // loaded assets are cached, so the file is only read once.
+/// fs::remove_file(root.join("dialog/intro.txt")).unwrap();
+/// assert_eq!(assets.load::<String>("./dialog/intro.txt").unwrap(), "Welcome!");
+/// assert!(assets.load::<String>("dialog/outro.txt").is_err());
+/// fs::remove_dir_all(&root).unwrap();
+/// ```
+pub struct AssetManager {
+    root: PathBuf,
+    loaders: HashMap<(TypeId, String), Box<dyn Any>>,
+    assets: AnyHolder,
+    watcher: Option<RefCell<Watcher>>,
+}
+
+impl AssetManager {
+    /// Constructs a new `AssetManager` without any loaders, which loads files relative to `root`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::AssetManager;
+    /// use std::path::Path;
+    ///
+    /// let assets = AssetManager::new("assets");
+    /// assert_eq!(assets.root(), Path::new("assets"));
+    /// assert!(assets.is_empty());
+    /// ```
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        AssetManager {
+            root: root.into(),
+            loaders: HashMap::new(),
+            assets: AnyHolder::new(),
+            watcher: None,
+        }
+    }
+
+    /// Returns the directory from which all assets are loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::AssetManager;
+    /// use std::path::Path;
+    ///
+    /// let assets = AssetManager::new("assets");
+    /// assert_eq!(assets.root(), Path::new("assets"));
+    /// ```
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Registers `loader` for all of its extensions.
+    ///
+    /// In case a loader for the same asset type and extension was already registered, it is replaced.
+    /// Loaders of different asset types may share extensions, as the asset type is chosen when calling [`load`](#method.load).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::{AssetManager, Loader};
+    /// use std::fs;
+    /// use std::process;
+    /// use std::str::{self, Utf8Error};
+    ///
+    /// struct BytesLoader;
+    ///
+    /// impl Loader for BytesLoader {
+    ///     type Asset = Vec<u8>;
+    ///     type Error = Utf8Error;
+    ///
+    ///     fn extensions(&self) -> &[&str] {
+    ///         &["bin", "txt"]
+    ///     }
+    ///
+    ///     fn load(&self, bytes: &[u8]) -> Result<Vec<u8>, Utf8Error> {
+    ///         Ok(bytes.to_vec())
+    ///     }
+    /// }
+    ///
+    /// struct LineCountLoader;
+    ///
+    /// impl Loader for LineCountLoader {
+    ///     type Asset = usize;
+    ///     type Error = Utf8Error;
+    ///
+    ///     fn extensions(&self) -> &[&str] {
+    ///         &["TXT"]
+    ///     }
+    ///
+    ///     fn load(&self, bytes: &[u8]) -> Result<usize, Utf8Error> {
+    ///         str::from_utf8(bytes).map(|text| text.lines().count())
+    ///     }
+    /// }
+    ///
+    /// let root = std::env::temp_dir().join(format!("crow_util_assets_add_loader_{}", process::id()));
+    /// fs::create_dir_all(&root).unwrap();
+    /// fs::write(root.join("credits.txt"), "Alice\nBob").unwrap();
+    ///
+    /// let mut assets = AssetManager::new(&root);
+    /// assets.add_loader(BytesLoader);
+    /// assets.add_loader(LineCountLoader);
+    ///
+    /// assert_eq!(assets.load::<Vec<u8>>("credits.txt").unwrap(), b"Alice\nBob");
+    /// assert_eq!(assets.load::<usize>("credits.txt").unwrap(), &2);
+    /// fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn add_loader<L: Loader + 'static>(&mut self, loader: L)
+    where L::Asset: 'static {
+        let extensions: Vec<String> = loader.extensions().iter()
+            .map(|extension| extension.to_ascii_lowercase())
+            .collect();
+
+        let load: LoadFn<L::Asset> = Rc::new(move |bytes| loader.load(bytes).map_err(Box::from));
+        for extension in extensions {
+            self.loaders.insert((TypeId::of::<L::Asset>(), extension), Box::new(load.clone()));
+        }
+    }
+
+    /// Returns a reference to the asset of type `T` at `path`, loading it in case it was not loaded yet.
+    ///
+    /// `path` is relative to the [`root`](#method.root) and both `/` and `\` are accepted as separators.
+    /// Absolute paths, paths with a prefix like `C:`, paths whose `..` components leave the root
+    /// and paths referring to the root itself, like `""` or `"."`, are rejected.
+    /// The loader is chosen by the extension of `path`. In case loading fails, nothing is cached,
+    /// so the next call tries to load the asset again.
+    /// This method can be used while `AssetManager` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::{AssetManager, LoadError, Loader};
+    /// use std::fs;
+    /// use std::process;
+    /// # use std::string::FromUtf8Error;
+    /// #
+    /// # struct TextLoader;
+    /// #
+    /// # impl Loader for TextLoader {
+    /// #     type Asset = String;
+    /// #     type Error = FromUtf8Error;
+    /// #
+    /// #     fn extensions(&self) -> &[&str] {
+    /// #         &["txt"]
+    /// #     }
+    /// #
+    /// #     fn load(&self, bytes: &[u8]) -> Result<String, FromUtf8Error> {
+    /// #         String::from_utf8(bytes.to_vec())
+    /// #     }
+    /// # }
+    ///
+    /// let root = std::env::temp_dir().join(format!("crow_util_assets_load_{}", process::id()));
+    /// fs::create_dir_all(&root).unwrap();
+    /// fs::write(root.join("name.txt"), "crow").unwrap();
+    /// fs::write(root.join("broken.txt"), [0xff, 0xfe]).unwrap();
+    ///
+    /// let mut assets = AssetManager::new(&root);
+    /// assets.add_loader(TextLoader);
+    ///
+    /// let name = assets.load::<String>("name.txt").unwrap();
+    /// assert_eq!(assets.load::<String>("sub/../name.txt").unwrap(), name);
+    ///
+    /// match assets.load::<String>("broken.txt") {
+    ///     Err(LoadError::Loader(path, _)) => assert_eq!(path, "broken.txt"),
+    ///     _ => unreachable!(),
+    /// }
+    /// match assets.load::<String>("missing.txt") {
+    ///     Err(LoadError::Io(path, _)) => assert_eq!(path, "missing.txt"),
+    ///     _ => unreachable!(),
+    /// }
+    /// match assets.load::<u32>("name.txt") {
+    ///     Err(LoadError::UnsupportedExtension(path)) => assert_eq!(path, "name.txt"),
+    ///     _ => unreachable!(),
+    /// }
+    /// match assets.load::<String>("sub/../../name.txt") {
+    ///     Err(LoadError::InvalidPath(path)) => assert_eq!(path, "sub/../../name.txt"),
+    ///     _ => unreachable!(),
+    /// }
+    /// assert!(assets.load::<String>("/etc/hostname.txt").is_err());
+    /// assert!(assets.load::<String>("C:\\name.txt").is_err());
+    /// for &path in &["", ".", "sub/.."] {
+    ///     match assets.load::<String>(path) {
+    ///         Err(LoadError::InvalidPath(_)) => {}
+    ///         _ => unreachable!(),
+    ///     }
+    /// }
+    /// fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn load<T: 'static>(&self, path: &str) -> Result<&T, LoadError> {
+        let key = normalize(path)?;
+        self.assets.holder::<T>().try_insert_fn(&key[..], || self.read_watched(&key))
+    }
+
+    /// Returns a reference to the asset of type `T` at `path` in case it was already loaded.
+    ///
+    /// Returns `None` for paths which are rejected by [`load`](#method.load).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::{AssetManager, Loader};
+    /// use std::fs;
+    /// use std::process;
+    /// # use std::string::FromUtf8Error;
+    /// #
+    /// # struct TextLoader;
+    /// #
+    /// # impl Loader for TextLoader {
+    /// #     type Asset = String;
+    /// #     type Error = FromUtf8Error;
+    /// #
+    /// #     fn extensions(&self) -> &[&str] {
+    /// #         &["txt"]
+    /// #     }
+    /// #
+    /// #     fn load(&self, bytes: &[u8]) -> Result<String, FromUtf8Error> {
+    /// #         String::from_utf8(bytes.to_vec())
+    /// #     }
+    /// # }
+    ///
+    /// let root = std::env::temp_dir().join(format!("crow_util_assets_get_{}", process::id()));
+    /// fs::create_dir_all(&root).unwrap();
+    /// fs::write(root.join("name.txt"), "crow").unwrap();
+    ///
+    /// let mut assets = AssetManager::new(&root);
+    /// assets.add_loader(TextLoader);
+    ///
+    /// assert_eq!(assets.get::<String>("name.txt"), None);
+    /// assets.load::<String>("name.txt").unwrap();
+    /// assert_eq!(assets.get::<String>("name.txt").unwrap(), "crow");
+    ///
+    /// let outside = format!("../{}/name.txt", root.file_name().unwrap().to_str().unwrap());
+    /// assert_eq!(assets.get::<String>(&outside), None);
+    /// fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn get<T: 'static>(&self, path: &str) -> Option<&T> {
+        normalize(path).ok().and_then(|key| self.assets.get(&key[..]))
+    }
+
+    /// Removes all loaded assets, while keeping the registered loaders.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::{AssetManager, Loader};
+    /// use std::fs;
+    /// use std::process;
+    /// # use std::string::FromUtf8Error;
+    /// #
+    /// # struct TextLoader;
+    /// #
+    /// # impl Loader for TextLoader {
+    /// #     type Asset = String;
+    /// #     type Error = FromUtf8Error;
+    /// #
+    /// #     fn extensions(&self) -> &[&str] {
+    /// #         &["txt"]
+    /// #     }
+    /// #
+    /// #     fn load(&self, bytes: &[u8]) -> Result<String, FromUtf8Error> {
+    /// #         String::from_utf8(bytes.to_vec())
+    /// #     }
+    /// # }
+    ///
+    /// let root = std::env::temp_dir().join(format!("crow_util_assets_clear_{}", process::id()));
+    /// fs::create_dir_all(&root).unwrap();
+    /// fs::write(root.join("name.txt"), "crow").unwrap();
+    ///
+    /// let mut assets = AssetManager::new(&root);
+    /// assets.add_loader(TextLoader);
+    /// assets.load::<String>("name.txt").unwrap();
+    ///
+    /// assets.clear();
+    /// assert!(assets.is_empty());
+    /// assert!(assets.load::<String>("name.txt").is_ok());
+    /// fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn clear(&mut self) {
+        self.assets.clear();
+        self.forget_watched();
+    }
+
+    /// Returns the number of loaded assets of every type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::{AssetManager, Loader};
+    /// use std::fs;
+    /// use std::process;
+    /// # use std::string::FromUtf8Error;
+    /// #
+    /// # struct TextLoader;
+    /// #
+    /// # impl Loader for TextLoader {
+    /// #     type Asset = String;
+    /// #     type Error = FromUtf8Error;
+    /// #
+    /// #     fn extensions(&self) -> &[&str] {
+    /// #         &["txt"]
+    /// #     }
+    /// #
+    /// #     fn load(&self, bytes: &[u8]) -> Result<String, FromUtf8Error> {
+    /// #         String::from_utf8(bytes.to_vec())
+    /// #     }
+    /// # }
+    ///
+    /// let root = std::env::temp_dir().join(format!("crow_util_assets_len_{}", process::id()));
+    /// fs::create_dir_all(&root).unwrap();
+    /// fs::write(root.join("a.txt"), "a").unwrap();
+    /// fs::write(root.join("b.txt"), "b").unwrap();
+    ///
+    /// let mut assets = AssetManager::new(&root);
+    /// assets.add_loader(TextLoader);
+    /// assets.load::<String>("a.txt").unwrap();
+    /// assets.load::<String>("b.txt").unwrap();
+    /// assets.load::<String>("./a.txt").unwrap();
+    /// assert_eq!(assets.len(), 2);
+    /// fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    /// Returns `true` if no assets are loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::AssetManager;
+    ///
+    /// let assets = AssetManager::new("assets");
+    /// assert!(assets.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Reads and decodes the file at the normalized path `key`.
+    fn read<T: 'static>(&self, key: &str) -> Result<T, LoadError> {
+        let load = self.loader::<T>(key)?;
+        self.read_with(key, load)
+    }
+
+    /// Reads the file at `key` and decodes it using `load`.
+    fn read_with<T>(&self, key: &str, load: &LoadFn<T>) -> Result<T, LoadError> {
+        let bytes = fs::read(self.root.join(key)).map_err(|error| LoadError::Io(key.to_owned(), error))?;
+        load(&bytes).map_err(|error| LoadError::Loader(key.to_owned(), error))
+    }
+
+    /// Returns the loader for assets of type `T` which supports the extension of `key`.
+    fn loader<T: 'static>(&self, key: &str) -> Result<&LoadFn<T>, LoadError> {
+        Path::new(key).extension().and_then(OsStr::to_str)
+            .and_then(|extension| self.loaders.get(&(TypeId::of::<T>(), extension.to_ascii_lowercase())))
+            .map(|load| load.downcast_ref::<LoadFn<T>>().unwrap())
+            .ok_or_else(|| LoadError::UnsupportedExtension(key.to_owned()))
+    }
+}
+
+/// The error returned by [`AssetManager::load`](struct.AssetManager.html#method.load), each variant contains the path of the asset.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The path is absolute, has a prefix, leaves the root directory or refers to the root directory itself,
+    /// this variant contains the path as it was passed.
+    InvalidPath(String),
+    /// No loader for the requested asset type supports the extension of the path.
+    UnsupportedExtension(String),
+    /// The file could not be read.
+    Io(String, io::Error),
+    /// The loader was not able to decode the content of the file.
+    Loader(String, Box<dyn Error>),
+}
+
+impl LoadError {
+    /// Returns the normalized path of the asset which could not be loaded, or the passed path in case it is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::AssetManager;
+    ///
+    /// let assets = AssetManager::new("assets");
+    /// let error = assets.load::<String>("./textures\\player.png").unwrap_err();
+    /// assert_eq!(error.path(), "textures/player.png");
+    /// assert_eq!(error.to_string(), "no loader supports 'textures/player.png'");
+    /// ```
+    pub fn path(&self) -> &str {
+        match *self {
+            LoadError::InvalidPath(ref path) |
+            LoadError::UnsupportedExtension(ref path) |
+            LoadError::Io(ref path, _) |
+            LoadError::Loader(ref path, _) => path,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::InvalidPath(ref path) => write!(f, "'{}' is not inside the root directory", path),
+            LoadError::UnsupportedExtension(ref path) => write!(f, "no loader supports '{}'", path),
+            LoadError::Io(ref path, ref error) => write!(f, "failed to read '{}': {}", path, error),
+            LoadError::Loader(ref path, ref error) => write!(f, "failed to load '{}': {}", path, error),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LoadError::InvalidPath(_) | LoadError::UnsupportedExtension(_) => None,
+            LoadError::Io(_, ref error) => Some(error),
+            LoadError::Loader(_, ref error) => Some(&**error),
+        }
+    }
+}
+
+/// Converts `path` into the key of an asset by removing empty and `.` components and resolving `..`.
+///
+/// Fails in case `path` is absolute, has a prefix like `C:`, a `..` component leaves the root directory
+/// or `path` refers to the root directory itself.
+fn normalize(path: &str) -> Result<String, LoadError> {
+    let invalid = || LoadError::InvalidPath(path.to_owned());
+    if path.starts_with(['/', '\\']) {
+        return Err(invalid());
+    }
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop().ok_or_else(invalid)?;
+            }
+            // drive letters and alternate data streams on Windows.
+            component if component.contains(':') => return Err(invalid()),
+            component => components.push(component),
+        }
+    }
+
+    if components.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(components.join("/"))
+}
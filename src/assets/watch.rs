@@ -0,0 +1,196 @@
+//! Reloading of assets whose files were changed.
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use super::{AssetManager, LoadError, LoadFn};
+
+/// Reloads the asset at `key` using the loader which originally loaded it.
+type ReloadFn = Rc<dyn Fn(&mut AssetManager, &str) -> Result<(), LoadError>>;
+
+/// Remembers the files of all assets loaded while watching is enabled.
+pub(super) struct Watcher {
+    files: HashMap<(TypeId, String), WatchedFile>,
+}
+
+struct WatchedFile {
+    modified: Option<SystemTime>,
+    reload: ReloadFn,
+}
+
+impl AssetManager {
+    /// Enables or disables watching the files of loaded assets, which is disabled by default.
+    ///
+    /// Only assets loaded while watching is enabled are reloaded by [`poll_changes`](#method.poll_changes).
+    /// Disabling watching forgets all watched files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::AssetManager;
+    ///
+    /// let mut assets = AssetManager::new("assets");
+    /// assert!(!assets.is_watching());
+    /// assets.set_watching(true);
+    /// assert!(assets.is_watching());
+    /// ```
+    pub fn set_watching(&mut self, watching: bool) {
+        if watching != self.is_watching() {
+            self.watcher = if watching {
+                Some(RefCell::new(Watcher { files: HashMap::new() }))
+            }
+            else {
+                None
+            };
+        }
+    }
+
+    /// Returns `true` if the files of loaded assets are watched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::AssetManager;
+    ///
+    /// let assets = AssetManager::new("assets");
+    /// assert!(!assets.is_watching());
+    /// ```
+    pub fn is_watching(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    /// Reloads every watched asset whose file was modified since it was loaded, returning their sorted paths.
+    ///
+    /// Changes are detected by comparing the modification time of each file, so this method
+    /// should be called periodically, for example once per frame during development.
+    /// In case reloading an asset fails, the old asset is kept and it is reloaded after the next change of its file.
+    /// Assets are reloaded by the loader which originally loaded them, even if another loader was added for their extension since.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::assets::{AssetManager, Loader};
+    /// use std::fs::{self, File};
+    /// use std::process;
+    /// use std::time::SystemTime;
+    /// # use std::string::FromUtf8Error;
+    /// #
+    /// # struct TextLoader;
+    /// #
+    /// # impl Loader for TextLoader {
+    /// #     type Asset = String;
+    /// #     type Error = FromUtf8Error;
+    /// #
+    /// #     fn extensions(&self) -> &[&str] {
+    /// #         &["txt"]
+    /// #     }
+    /// #
+    /// #     fn load(&self, bytes: &[u8]) -> Result<String, FromUtf8Error> {
+    /// #         String::from_utf8(bytes.to_vec())
+    /// #     }
+    /// # }
+    /// #
+    /// # struct UppercaseLoader;
+    /// #
+    /// # impl Loader for UppercaseLoader {
+    /// #     type Asset = String;
+    /// #     type Error = FromUtf8Error;
+    /// #
+    /// #     fn extensions(&self) -> &[&str] {
+    /// #         &["txt"]
+    /// #     }
+    /// #
+    /// #     fn load(&self, bytes: &[u8]) -> Result<String, FromUtf8Error> {
+    /// #         String::from_utf8(bytes.to_ascii_uppercase())
+    /// #     }
+    /// # }
+    ///
+    /// let root = std::env::temp_dir().join(format!("crow_util_assets_poll_changes_{}", process::id()));
+    /// fs::create_dir_all(&root).unwrap();
+    /// fs::write(root.join("a.txt"), "old a").unwrap();
+    /// fs::write(root.join("b.txt"), "old b").unwrap();
+    ///
+    /// let mut assets = AssetManager::new(&root);
+    /// assets.add_loader(TextLoader);
+    /// assets.set_watching(true);
+    /// assets.load::<String>("a.txt").unwrap();
+    /// assets.load::<String>("b.txt").unwrap();
+    /// assert!(assets.poll_changes().is_empty());
+    ///
+    /// // only assets loaded from now on use the new loader.
+    /// assets.add_loader(UppercaseLoader);
+    /// fs::write(root.join("a.txt"), "new a").unwrap();
+    /// // some file systems only store the modification time in seconds.
+    /// File::options().write(true).open(root.join("a.txt")).unwrap()
+    ///     .set_modified(SystemTime::UNIX_EPOCH).unwrap();
+    ///
+    /// assert_eq!(assets.poll_changes(), ["a.txt"]);
+    /// assert_eq!(assets.get::<String>("a.txt").unwrap(), "new a");
+    /// assert_eq!(assets.get::<String>("b.txt").unwrap(), "old b");
+    /// assert!(assets.poll_changes().is_empty());
+    /// fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn poll_changes(&mut self) -> Vec<String> {
+        let mut changed = Vec::new();
+        if let Some(ref mut watcher) = self.watcher {
+            for ((_, key), file) in &mut watcher.get_mut().files {
+                let modified = modified(&self.root.join(key));
+                if modified != file.modified {
+                    file.modified = modified;
+                    changed.push((key.clone(), file.reload.clone()));
+                }
+            }
+        }
+
+        let mut reloaded: Vec<String> = changed.into_iter()
+            .filter(|(key, reload)| reload(self, key).is_ok())
+            .map(|(key, _)| key)
+            .collect();
+        reloaded.sort_unstable();
+        reloaded.dedup();
+        reloaded
+    }
+
+    /// Reads and decodes the file at `key`, watching it for changes in case watching is enabled.
+    pub(super) fn read_watched<T: 'static>(&self, key: &str) -> Result<T, LoadError> {
+        let watcher = match self.watcher {
+            Some(ref watcher) => watcher,
+            None => return self.read(key),
+        };
+
+        // the modification time is checked before reading, so changes during reading are detected by the next poll.
+        let load = self.loader::<T>(key)?.clone();
+        let modified = modified(&self.root.join(key));
+        let asset = self.read_with(key, &load)?;
+        watcher.borrow_mut().files.insert((TypeId::of::<T>(), key.to_owned()), WatchedFile {
+            modified,
+            reload: Rc::new(move |assets, key| reload(assets, key, &load)),
+        });
+        Ok(asset)
+    }
+
+    /// Forgets all watched files, used after all assets were removed.
+    pub(super) fn forget_watched(&mut self) {
+        if let Some(ref mut watcher) = self.watcher {
+            watcher.get_mut().files.clear();
+        }
+    }
+}
+
+/// Replaces the asset of type `T` at `key` with the current content of its file.
+fn reload<T: 'static>(assets: &mut AssetManager, key: &str, load: &LoadFn<T>) -> Result<(), LoadError> {
+    let asset = assets.read_with(key, load)?;
+    if let Some(holder) = assets.assets.holder_mut::<T>() {
+        holder.replace(key, asset);
+    }
+    Ok(())
+}
+
+/// Returns the modification time of the file at `path`, or `None` in case it does not exist.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -1,8 +1,65 @@
 //! A module containing a basic holder struct, which is used for immutable access to its elements while still being able to insert new elements.
 //!
 //! For examples and further explanation, visit [`Holder<T>`](struct.Holder.html).
+//! In case elements have to be shared between threads, use [`SyncHolder<T>`](struct.SyncHolder.html) instead.
+//! Once no more elements are inserted, [`Holder::freeze`](struct.Holder.html#method.freeze) creates a [`FrozenHolder<T>`](struct.FrozenHolder.html) with faster lookups.
+//! For hierarchical keys like `textures/player/idle_03` which require prefix queries, use [`PrefixHolder<T>`](struct.PrefixHolder.html).
+//! To drop elements once they are no longer used, use [`CountedHolder<T>`](struct.CountedHolder.html).
+//! In case the iteration order has to be the same in every run, use [`OrderedHolder<T>`](struct.OrderedHolder.html), which iterates in insertion order.
+use std::borrow::Borrow;
+#[cfg(feature = "stats")]
+use std::cell::Cell;
 use std::cell::UnsafeCell;
+use std::convert::Infallible;
 use std::collections::HashMap;
+use std::collections::hash_map::{self, RandomState};
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Index;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use traits::RetainMut;
+
+mod any;
+mod arena;
+mod counted;
+mod frozen;
+#[cfg(any(feature = "fxhash", feature = "ahash"))]
+mod hasher;
+mod ordered;
+mod prefix;
+mod scoped;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "stats")]
+mod stats;
+mod suggest;
+mod sync;
+mod versioned;
+
+pub use self::any::AnyHolder;
+pub use self::arena::ArenaHolder;
+pub use self::counted::{CountedHolder, HolderRc};
+pub use self::frozen::{FrozenHolder, FrozenIter};
+#[cfg(feature = "ahash")]
+pub use self::hasher::AHolder;
+#[cfg(feature = "fxhash")]
+pub use self::hasher::{FxBuildHasher, FxHasher, FxHolder};
+pub use self::ordered::{OrderedHolder, OrderedIter};
+pub use self::prefix::{PrefixHolder, PrefixIter, Subtree};
+pub use self::scoped::ScopedHolder;
+#[cfg(feature = "stats")]
+pub use self::stats::HolderStats;
+pub use self::suggest::LookupError;
+pub use self::sync::SyncHolder;
+pub use self::versioned::VersionedHolder;
+
+#[cfg(feature = "stats")]
+use self::stats::Counters;
 
 
 /// A Hashmap which allows for immutable access while still allowing the addition of new objects.
@@ -36,11 +93,53 @@ use std::collections::HashMap;
 /// holder.shrink_to_fit();
 /// assert_eq!(holder.capacity(),0);
 /// ```
-pub struct Holder<T: ?Sized> {
-    items: UnsafeCell<HashMap<String,Box<T>>>,
+///
+/// The key type `K` defaults to `String`, but any type implementing `Eq` and `Hash` can be used.
+/// Elements can be accessed using any borrowed form of the key, for example `&str` for `String` or `&Path` for `PathBuf`.
+///
+/// ```
+/// use crow_util::holder;
+/// use std::path::{Path, PathBuf};
+///
+/// let textures: holder::Holder<u32, PathBuf> = holder::Holder::new();
+/// textures.insert(Path::new("textures/player.png"), 7);
+/// assert_eq!(textures.get(Path::new("textures/player.png")), Some(&7));
+///
+/// #[derive(Clone, PartialEq, Eq, Hash)]
+/// enum AssetId {
+///     Player,
+///     Enemy,
+/// }
+///
+/// let meshes = holder::Holder::new();
+/// meshes.insert(&AssetId::Player, "player mesh");
+/// assert_eq!(meshes.get(&AssetId::Player), Some(&"player mesh"));
+/// assert_eq!(meshes.get(&AssetId::Enemy), None);
+///
+/// let hashed = holder::Holder::new();
+/// hashed.insert(&0x1234_u64, "sound");
+/// assert_eq!(hashed.get(&0x1234), Some(&"sound"));
+/// ```
+///
+/// Just like `HashMap`, `Holder<T>` uses `RandomState` by default, which is resistant against HashDoS attacks.
+/// For trusted keys like asset names, a faster hasher can be used with [`with_hasher`](#method.with_hasher).
+/// The `fxhash` feature provides such a hasher together with the [`FxHolder<T>`](type.FxHolder.html) alias,
+/// the `ahash` feature provides the randomly seeded aHash together with the [`AHolder<T>`](type.AHolder.html) alias.
+///
+/// With the `stats` feature enabled, `Holder<T>` counts its lookups and insertions, see [`stats`](#method.stats).
+/// Without it, no counters are stored and nothing is counted.
+///
+/// With the `serde` feature enabled, `Holder<T>` implements `Serialize` and `Deserialize` as a map from keys to elements.
+pub struct Holder<T: ?Sized, K = String, S = RandomState> {
+    items: UnsafeCell<HashMap<StableBox<K>,Slot<T>,S>>,
+    ids: UnsafeCell<Ids<T, K>>,
+    #[cfg(feature = "stats")]
+    counters: Counters,
 }
 
-impl<T> Holder<T> {
+unsafe impl<T: ?Sized + Send, K: Send, S: Send> Send for Holder<T, K, S> {}
+
+impl<T: ?Sized, K: Eq + Hash> Holder<T, K> {
     /// Constructs a new, empty `Holder<T>`.
     ///
     /// # Examples
@@ -52,9 +151,7 @@ impl<T> Holder<T> {
     /// assert_eq!(holder.len(),0);
     /// ```
     pub fn new() -> Self {
-        Holder {
-            items: UnsafeCell::new(HashMap::new()),
-        }
+        Holder::with_hasher(RandomState::new())
     }
 
     /// Constructs a new, empty `Holder<T>` with the specified capacity.
@@ -70,11 +167,63 @@ impl<T> Holder<T> {
     /// # holder.insert("hidden", 420);
     /// ```
     pub fn with_capacity(capacity: usize) -> Self {
+        Holder::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<T: ?Sized, K: Eq + Hash, S: BuildHasher> Holder<T, K, S> {
+    /// Constructs a new, empty `Holder<T>` which uses `hasher` to hash its keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let holder = holder::Holder::with_hasher(RandomState::new());
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder.get("a"), Some(&42));
+    /// ```
+    pub fn with_hasher(hasher: S) -> Self {
+        Holder::with_capacity_and_hasher(0, hasher)
+    }
+
+    /// Constructs a new, empty `Holder<T>` with the specified capacity, which uses `hasher` to hash its keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let holder = holder::Holder::with_capacity_and_hasher(42, RandomState::new());
+    /// assert!(holder.capacity() >= 42);
+    /// # holder.insert("hidden", 420);
+    /// ```
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
         Holder {
-            items: UnsafeCell::new(HashMap::with_capacity(capacity)),
+            items: UnsafeCell::new(HashMap::with_capacity_and_hasher(capacity, hasher)),
+            ids: UnsafeCell::new(Ids::with_capacity(capacity)),
+            #[cfg(feature = "stats")]
+            counters: Counters::default(),
         }
     }
 
+    /// Returns a reference to the hasher used to hash the keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let holder: holder::Holder<u32> = holder::Holder::new();
+    /// let _: &RandomState = holder.hasher();
+    /// ```
+    pub fn hasher(&self) -> &S {
+        unsafe { & *self.items.get() }.hasher()
+    }
+
     /// Returns a reference to the element corresponding to the key.
     ///
     /// # Examples
@@ -86,13 +235,79 @@ impl<T> Holder<T> {
     /// holder.insert("a", 42);
     /// assert_eq!(holder.get("a"), Some(&42));
     /// ```
-    pub fn get(&self, key: &str) -> Option<&T> {
-        let items = unsafe {& *self.items.get() };
-        items.get(key).map(|v| &**v)
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let slot = self.get_slot(key);
+        #[cfg(feature = "stats")]
+        self.counters.record_lookup(slot);
+        slot.map(|slot| slot.element.get())
     }
 
-    /// Inserts an `element` accessible by `key`.
-    /// 
+    /// Returns the id of the element corresponding to the key.
+    ///
+    /// Ids can be used to access elements using [`get_by_id`](#method.get_by_id) without hashing the key.
+    /// They stay valid until the element is removed or the `Holder<T>` is cleared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    ///
+    /// let id = holder.id("a").unwrap();
+    /// assert_eq!(holder.get_by_id(id), Some(&42));
+    /// assert_eq!(holder.id("b"), None);
+    /// ```
+    pub fn id<Q>(&self, key: &Q) -> Option<HolderId<T>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let ids = unsafe {& *self.ids.get() };
+        self.get_slot(key).map(|slot| ids.id(slot.id))
+    }
+
+    /// Returns a reference to the element corresponding to the `id`.
+    ///
+    /// This is a simple index lookup, making it faster than [`get`](#method.get).
+    /// Returns `None` in case `id` does not belong to an element of this `Holder<T>`, which can happen if the
+    /// `id` was created by a different `Holder<T>`, its element was removed or the `Holder<T>` was cleared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// let id = holder.insert_id("player", 42);
+    ///
+    /// // resolve the name once and use the id every frame.
+    /// for _ in 0..60 {
+    ///     assert_eq!(holder.get_by_id(id), Some(&42));
+    /// }
+    ///
+    /// holder.clear();
+    /// holder.insert("enemy", 7);
+    /// assert_eq!(holder.get_by_id(id), None);
+    ///
+    /// // ids of removed elements stay invalid, even once their memory is reused.
+    /// let id = holder.id("enemy").unwrap();
+    /// holder.remove("enemy");
+    /// holder.insert("boss", 99);
+    /// assert_eq!(holder.get_by_id(id), None);
+    ///
+    /// // ids of other holders are rejected.
+    /// let other = holder::Holder::new();
+    /// other.insert("player", 42);
+    /// assert_eq!(holder.get_by_id(other.id("player").unwrap()), None);
+    /// ```
+    pub fn get_by_id(&self, id: HolderId<T>) -> Option<&T> {
+        let ids = unsafe {& *self.ids.get() };
+        ids.get(id).map(|ptr| unsafe { &*ptr.as_ptr() })
+    }
+
+    /// Inserts an already boxed `element` accessible by `key`.
+    ///
+    /// Unlike [`insert`](#method.insert), this method also works for unsized types like `str`, `[u8]` or trait objects.
     /// In case the `key` was already present, the old `element` is returned and the new one is ignored.
     /// This method can be used while `Holder<T>` is already immutably borrowed.
     ///
@@ -100,66 +315,113 @@ impl<T> Holder<T> {
     ///
     /// ```
     /// use crow_util::holder;
+    /// use std::fmt::Display;
     ///
-    /// let holder = holder::Holder::new();
-    /// assert_eq!(holder.insert("a", 42), None);
+    /// let strings: holder::Holder<str> = holder::Holder::new();
+    /// strings.insert_boxed("greeting", "hello".into());
+    /// assert_eq!(strings.get("greeting"), Some("hello"));
     ///
-    /// let val = holder.get("a");
-    /// assert_eq!(holder.insert("a", 25), val);
+    /// let blobs: holder::Holder<[u8]> = holder::Holder::new();
+    /// blobs.insert_boxed("blob", vec![1, 2, 3].into_boxed_slice());
+    /// assert_eq!(blobs.insert_boxed("blob", Box::new([4])), Some(&[1, 2, 3][..]));
     ///
-    /// holder.insert("b",43);
-    /// assert_eq!(holder.len(),2);
+    /// let displays: holder::Holder<dyn Display> = holder::Holder::new();
+    /// displays.insert_boxed("answer", Box::new(42));
+    /// assert_eq!(displays.get("answer").unwrap().to_string(), "42");
     /// ```
-    pub fn insert(&self, key: &str, element: T) -> Option<&T> {
-        let items = unsafe {&mut *self.items.get() };
-        if items.contains_key(key) {
-            items.get(key).map(|v| &**v)
+    pub fn insert_boxed<Q>(&self, key: &Q, element: Box<T>) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.insert_full(key, element) {
+            (existing, false) => Some(existing),
+            (_, true) => None,
         }
-        else {
-            assert!(items.insert(key.to_owned(), Box::new(element)).is_none());
-            None
+    }
+
+    /// Inserts an already boxed `element`, which is created by a closure and can be accessed by `key`.
+    ///
+    /// This is the equivalent of [`insert_fn`](#method.insert_fn) which also works for unsized types.
+    /// In case the `key` was already present, the old `element` is returned and the closure is not called.
+    /// Just like with `insert_fn`, the closure itself may use this `Holder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::Holder<str> = holder::Holder::new();
+    /// assert_eq!(holder.insert_boxed_fn("a", || "first".into()), None);
+    /// assert_eq!(holder.insert_boxed_fn("a", || unreachable!()), Some("first"));
+    /// ```
+    pub fn insert_boxed_fn<Q, F>(&self, key: &Q, element: F) -> Option<&T>
+    where F: FnOnce() -> Box<T>,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.insert_with(key, || Ok::<_, Infallible>(element())) {
+            Ok((existing, false)) => Some(existing),
+            Ok((_, true)) => None,
+            Err(never) => match never {},
         }
     }
 
-    /// Inserts an `element`, which is created by a closure and can be accessed by `key`,
-    /// returning a usable reference `element` corresponding to this `key`.
-    /// In case the `key` was already present, the old `element` is returned and the new one is ignored.
-    /// This method can be used while `Holder<T>` is already immutably borrowed.
+    /// Tries to insert an already boxed `element`, which is created by a fallible closure and can be accessed by `key`.
     ///
-    /// This is useful in case performance is important, due to the fact that the closure is only called in
-    /// case the `key` does not already exist.
+    /// This is the equivalent of [`try_insert_fn`](#method.try_insert_fn) which also works for unsized types.
     ///
     /// # Examples
     ///
     /// ```
     /// use crow_util::holder;
     ///
-    /// fn complex_calculation(x: f64, y: f64) -> f64 {
-    /// #   1.0*x*y
-    ///     // ... this function is really long and complex.
-    /// }
+    /// let holder: holder::Holder<[u8]> = holder::Holder::new();
+    /// let bytes = vec![1, 2, 3];
+    /// assert_eq!(holder.try_insert_boxed_fn("a", || Ok::<_, ()>(bytes.into_boxed_slice())), Ok(&[1, 2, 3][..]));
+    /// assert_eq!(holder.try_insert_boxed_fn("b", || Err("missing file")), Err("missing file"));
+    /// assert_eq!(holder.len(), 1);
+    /// ```
+    pub fn try_insert_boxed_fn<Q, E, F>(&self, key: &Q, element: F) -> Result<&T, E>
+    where F: FnOnce() -> Result<Box<T>, E>,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.insert_with(key, element).map(|(element, _)| element)
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting the already boxed `element` in case
+    /// the `key` was not present.
     ///
-    /// let holder = holder::Holder::new();
+    /// This is the equivalent of [`get_or_insert`](#method.get_or_insert) which also works for unsized types.
     ///
-    /// // this calls complex_calculation() only once.
-    /// for _ in 0..10_000 {
-    ///     holder.insert_fn("a", || complex_calculation(2.0, 42.0));
-    /// }
+    /// # Examples
     ///
-    /// // this calls complex_calculation() 10_000 times,
-    /// for _ in 0..10_000 {
-    ///     holder.insert("b", complex_calculation(2.0, 42.0));
-    /// }
     /// ```
-    pub fn insert_fn<F>(&self, key: &str, element: F) -> Option<&T>
-    where F: Fn() -> T {
-        let items = unsafe {&mut *self.items.get() };
-        if items.contains_key(key) {
-            items.get(key).map(|v| &**v)
-        }
-        else {
-            assert!(items.insert(key.to_owned(), Box::new(element())).is_none());
-            None
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::Holder<str> = holder::Holder::new();
+    /// assert_eq!(holder.get_or_insert_boxed("a", "first".into()), "first");
+    /// assert_eq!(holder.get_or_insert_boxed("a", "second".into()), "first");
+    /// ```
+    pub fn get_or_insert_boxed<Q>(&self, key: &Q, element: Box<T>) -> &T
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.insert_full(key, element).0
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an already boxed element created by
+    /// the closure in case the `key` was not present.
+    ///
+    /// This is the equivalent of [`get_or_insert_with`](#method.get_or_insert_with) which also works for unsized types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::Holder<[u8]> = holder::Holder::new();
+    /// assert_eq!(holder.get_or_insert_boxed_with("a", || Box::new([1, 2])), &[1, 2]);
+    /// assert_eq!(holder.get_or_insert_boxed_with("a", || unreachable!()), &[1, 2]);
+    /// ```
+    pub fn get_or_insert_boxed_with<Q, F>(&self, key: &Q, element: F) -> &T
+    where F: FnOnce() -> Box<T>,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.insert_with(key, || Ok::<_, Infallible>(element())) {
+            Ok((element, _)) => element,
+            Err(never) => match never {},
         }
     }
 
@@ -183,6 +445,7 @@ impl<T> Holder<T> {
     #[inline(always)]
     pub fn clear(&mut self) {
         unsafe { &mut *self.items.get() }.clear();
+        unsafe { &mut *self.ids.get() }.clear();
     }
 
     /// Returns the number of elements in the map.
@@ -203,6 +466,23 @@ impl<T> Holder<T> {
         unsafe { & *self.items.get() }.len()
     }
 
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// assert!(holder.is_empty());
+    /// holder.insert("a", 42);
+    /// assert!(!holder.is_empty());
+    /// ```
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        unsafe { & *self.items.get() }.is_empty()
+    }
+
     /// Shrinks the capacity of the map as much as possible. It will drop down as much as possible while maintaining the internal rules and possibly leaving some space in accordance with the resize policy.
     ///
     /// # Examples
@@ -221,6 +501,7 @@ impl<T> Holder<T> {
     #[inline(always)]
     pub fn shrink_to_fit(&mut self) {
         unsafe { &mut *self.items.get() }.shrink_to_fit();
+        unsafe { &mut *self.ids.get() }.shrink_to_fit();
     }
 
     /// Returns the number of elements the map can hold without reallocating.
@@ -246,4 +527,1496 @@ impl<T> Holder<T> {
     pub fn capacity(&self) -> usize {
         unsafe { & *self.items.get() }.capacity()
     }
+
+    /// An iterator visiting all `key`-`element` pairs in arbitrary order.
+    ///
+    /// New elements can still be inserted while iterating, but only the elements present
+    /// when calling this method are visited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    ///
+    /// for (key, element) in holder.iter() {
+    ///     holder.insert(&format!("{}{}", key, key), *element * 2);
+    /// }
+    ///
+    /// assert_eq!(holder.len(), 4);
+    /// assert_eq!(holder.get("bb"), Some(&4));
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T, K> {
+        let ids = unsafe { & *self.ids.get() };
+        Iter {
+            ids: self.ids.get(),
+            index: 0,
+            insertions: ids.insertions,
+            remaining: self.len(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    ///
+    /// Just like [`iter`](#method.iter), this iterator allows insertions while iterating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    ///
+    /// let mut keys: Vec<&String> = holder.keys().collect();
+    /// keys.sort();
+    /// assert_eq!(keys, ["a", "b"]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_, T, K> {
+        Keys {
+            inner: self.iter(),
+        }
+    }
+
+    /// An iterator visiting all elements in arbitrary order.
+    ///
+    /// Just like [`iter`](#method.iter), this iterator allows insertions while iterating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    ///
+    /// assert_eq!(holder.values().sum::<u32>(), 3);
+    /// ```
+    pub fn values(&self) -> Values<'_, T, K> {
+        Values {
+            inner: self.iter(),
+        }
+    }
+
+    /// An iterator visiting all `key`-`element` pairs in arbitrary order, with mutable references to the elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    ///
+    /// for (_, element) in holder.iter_mut() {
+    ///     *element *= 10;
+    /// }
+    ///
+    /// assert_eq!(holder.get("b"), Some(&20));
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, K> {
+        IterMut {
+            inner: unsafe { &mut *self.items.get() }.iter_mut(),
+        }
+    }
+
+    /// Returns a mutable reference to the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    ///
+    /// if let Some(x) = holder.get_mut("a") {
+    ///     *x += 1;
+    /// }
+    /// assert_eq!(holder.get("a"), Some(&43));
+    /// ```
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.items.get_mut().get_mut(Query::new(key)).map(|slot| slot.element.get_mut())
+    }
+
+    /// Removes the element corresponding to the key, returning it as a `Box<T>`.
+    ///
+    /// This is the equivalent of [`remove`](#method.remove) which also works for unsized types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder: holder::Holder<str> = holder::Holder::new();
+    /// holder.insert_boxed("a", "hello".into());
+    /// assert_eq!(holder.remove_boxed("a"), Some("hello".into()));
+    /// assert_eq!(holder.remove_boxed("a"), None);
+    /// ```
+    pub fn remove_boxed<Q>(&mut self, key: &Q) -> Option<Box<T>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let slot = self.items.get_mut().remove(Query::new(key))?;
+        self.ids.get_mut().remove(slot.id);
+        Some(slot.element.into_box())
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all pairs `(k, e)` such that `f(&k, &mut e)` returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.insert("level_1/tiles", 1);
+    /// holder.insert("level_2/tiles", 2);
+    /// holder.insert("player", 3);
+    ///
+    /// // evict the assets of the first level.
+    /// holder.retain(|key, _| !key.starts_with("level_1/"));
+    /// assert_eq!(holder.len(), 2);
+    /// assert_eq!(holder.get("level_1/tiles"), None);
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where F: FnMut(&K, &mut T) -> bool {
+        let ids = self.ids.get_mut();
+        self.items.get_mut().retain(|key, slot| {
+            let keep = f(key.get(), slot.element.get_mut());
+            if !keep {
+                ids.remove(slot.id);
+            }
+            keep
+        });
+    }
+
+    /// Gets the entry corresponding to `key` for in-place manipulation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// for word in "a b a c a".split(' ') {
+    ///     *holder.entry(word.to_string()).or_insert(0) += 1;
+    /// }
+    ///
+    /// assert_eq!(holder.get("a"), Some(&3));
+    /// assert_eq!(holder.get("c"), Some(&1));
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, T, K, S> {
+        let ids = self.ids.get_mut();
+        let items = self.items.get_mut();
+        // the key is only boxed in case it is not already present.
+        match items.get_key_value(Query::new(&key)).map(|(key, slot)| (key.ptr, slot.element.ptr)) {
+            Some((key, element)) => Entry::Occupied(OccupiedEntry { items, ids, key, element }),
+            None => match items.entry(StableBox::new(key)) {
+                hash_map::Entry::Vacant(inner) => Entry::Vacant(VacantEntry { inner, ids }),
+                hash_map::Entry::Occupied(_) => unreachable!(),
+            },
+        }
+    }
+
+    /// Inserts the element created by the fallible closure in case `key` is not already present.
+    ///
+    /// Returns a reference to the element corresponding to `key` and whether the new element was inserted.
+    fn insert_with<Q, E, F>(&self, key: &Q, element: F) -> Result<(&T, bool), E>
+    where F: FnOnce() -> Result<Box<T>, E>,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        if let Some(existing) = self.get_slot(key) {
+            #[cfg(feature = "stats")]
+            self.counters.record_insert(false);
+            return Ok((existing.element.get(), false));
+        }
+
+        // the closure must be called while no mutable reference to `items` exists,
+        // as it is allowed to use this holder itself.
+        #[cfg(feature = "stats")]
+        self.counters.record_construction();
+        let element = element()?;
+        Ok(self.insert_full(key, element))
+    }
+
+    /// Inserts `element` in case `key` is not already present.
+    ///
+    /// Returns a reference to the element corresponding to `key` and whether `element` was inserted.
+    fn insert_full<Q>(&self, key: &Q, element: Box<T>) -> (&T, bool)
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        let (slot, inserted) = self.insert_slot(key, element);
+        (slot.element.get(), inserted)
+    }
+
+    /// Inserts `element` in case `key` is not already present.
+    ///
+    /// Returns the slot corresponding to `key` and whether `element` was inserted.
+    fn insert_slot<Q>(&self, key: &Q, element: Box<T>) -> (&Slot<T>, bool)
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        let (slot, inserted) = match self.get_slot(key) {
+            Some(existing) => (existing, false),
+            None => (self.push_slot(key.to_owned(), element), true),
+        };
+        #[cfg(feature = "stats")]
+        self.counters.record_insert(inserted);
+        (slot, inserted)
+    }
+
+    /// Inserts `element` at `key` in case `key` is not already present, without allocating a new key.
+    fn insert_owned(&self, key: K, element: Box<T>) {
+        let inserted = self.get_slot(&key).is_none();
+        if inserted {
+            self.push_slot(key, element);
+        }
+        #[cfg(feature = "stats")]
+        self.counters.record_insert(inserted);
+    }
+
+    /// Inserts a new slot, `key` must not be present.
+    fn push_slot(&self, key: K, element: Box<T>) -> &Slot<T> {
+        let items = unsafe {&mut *self.items.get() };
+        let ids = unsafe {&mut *self.ids.get() };
+        let key = StableBox::new(key);
+        let element = StableBox::from_box(element);
+        let slot = Slot::new(ids.insert(key.ptr, element.ptr), element);
+        items.entry(key).or_insert(slot)
+    }
+
+    fn get_slot<Q>(&self, key: &Q) -> Option<&Slot<T>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let items = unsafe {& *self.items.get() };
+        items.get(Query::new(key))
+    }
+}
+
+impl<T, K: Eq + Hash, S: BuildHasher> Holder<T, K, S> {
+    /// Inserts an `element` accessible by `key`.
+    /// 
+    /// In case the `key` was already present, the old `element` is returned and the new one is ignored.
+    /// This method can be used while `Holder<T>` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// assert_eq!(holder.insert("a", 42), None);
+    ///
+    /// let val = holder.get("a");
+    /// assert_eq!(holder.insert("a", 25), val);
+    ///
+    /// holder.insert("b",43);
+    /// assert_eq!(holder.len(),2);
+    /// ```
+    pub fn insert<Q>(&self, key: &Q, element: T) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.insert_boxed(key, Box::new(element))
+    }
+
+    /// Inserts an `element` accessible by `key`, returning the id of the `element` corresponding to this `key`.
+    ///
+    /// In case the `key` was already present, the id of the old `element` is returned and the new one is ignored.
+    /// This method can be used while `Holder<T>` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// let a = holder.insert_id("a", 42);
+    /// let b = holder.insert_id("b", 360);
+    /// assert_ne!(a, b);
+    /// assert_eq!(holder.insert_id("a", 25), a);
+    /// assert_eq!(holder.get_by_id(a), Some(&42));
+    /// ```
+    pub fn insert_id<Q>(&self, key: &Q, element: T) -> HolderId<T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        let id = self.insert_slot(key, Box::new(element)).0.id;
+        unsafe { & *self.ids.get() }.id(id)
+    }
+
+    /// Inserts an `element`, which is created by a closure and can be accessed by `key`.
+    /// In case the `key` was already present, the old `element` is returned and the new one is ignored.
+    /// This method can be used while `Holder<T>` is already immutably borrowed.
+    ///
+    /// This is useful in case performance is important, due to the fact that the closure is only called in
+    /// case the `key` does not already exist.
+    ///
+    /// The closure is called before the `Holder<T>` is modified, so it may itself get or insert elements of this `Holder<T>`.
+    /// In case the closure inserts an element with the same `key`, this element is kept and returned,
+    /// while the element created by the closure is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// fn complex_calculation(x: f64, y: f64) -> f64 {
+    /// #   1.0*x*y
+    ///     // ... this function is really long and complex.
+    /// }
+    ///
+    /// let holder = holder::Holder::new();
+    ///
+    /// // this calls complex_calculation() only once.
+    /// for _ in 0..10_000 {
+    ///     holder.insert_fn("a", || complex_calculation(2.0, 42.0));
+    /// }
+    ///
+    /// // this calls complex_calculation() 10_000 times,
+    /// for _ in 0..10_000 {
+    ///     holder.insert("b", complex_calculation(2.0, 42.0));
+    /// }
+    /// ```
+    ///
+    /// The closure can use the `Holder<T>` it is inserted into:
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    ///
+    /// // the atlas loads its sub-textures while being inserted.
+    /// holder.insert_fn("atlas", || {
+    ///     let left = holder.insert_fn("atlas/left", || 1).map_or(1, |v| *v);
+    ///     let right = holder.insert_fn("atlas/right", || 2).map_or(2, |v| *v);
+    ///     assert_eq!(holder.get("atlas"), None);
+    ///     left + right
+    /// });
+    /// assert_eq!(holder.get("atlas"), Some(&3));
+    /// assert_eq!(holder.len(), 3);
+    ///
+    /// // inserting the same key inside of the closure keeps the inner element.
+    /// assert_eq!(holder.insert_fn("self", || {
+    ///     holder.insert("self", 7);
+    ///     42
+    /// }), Some(&7));
+    /// ```
+    pub fn insert_fn<Q, F>(&self, key: &Q, element: F) -> Option<&T>
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.insert_boxed_fn(key, || Box::new(element()))
+    }
+
+    /// Tries to insert an `element`, which is created by a fallible closure and can be accessed by `key`,
+    /// returning a reference to the `element` corresponding to this `key`.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the closure is not called.
+    /// In case the closure returns an error, nothing is inserted and the error is returned instead.
+    /// Just like with [`insert_fn`](#method.insert_fn), the closure itself may use this `Holder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// fn load(name: &str) -> Result<String, String> {
+    ///     if name.ends_with(".txt") {
+    ///         Ok(format!("content of {}", name))
+    ///     }
+    ///     else {
+    ///         Err(format!("unknown format: {}", name))
+    ///     }
+    /// }
+    ///
+    /// let holder = holder::Holder::new();
+    /// assert_eq!(holder.try_insert_fn("a", || load("a.txt")).map(|s| &s[..]), Ok("content of a.txt"));
+    /// assert_eq!(holder.try_insert_fn("b", || load("b.png")), Err("unknown format: b.png".to_string()));
+    /// assert_eq!(holder.get("b"), None);
+    ///
+    /// // the closure is not called in case the key already exists.
+    /// assert!(holder.try_insert_fn("a", || load("b.png")).is_ok());
+    /// ```
+    pub fn try_insert_fn<Q, E, F>(&self, key: &Q, element: F) -> Result<&T, E>
+    where F: FnOnce() -> Result<T, E>,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.try_insert_boxed_fn(key, || element().map(Box::new))
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting `element` in case the `key` was not present.
+    ///
+    /// Unlike [`insert`](#method.insert), this method always returns a reference to the `element` stored at `key`.
+    /// This method can be used while `Holder<T>` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// let a = holder.get_or_insert("a", 42);
+    /// assert_eq!(holder.get_or_insert("a", 25), a);
+    /// assert_eq!(*a, 42);
+    /// ```
+    pub fn get_or_insert<Q>(&self, key: &Q, element: T) -> &T
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.get_or_insert_boxed(key, Box::new(element))
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an element created by
+    /// the closure in case the `key` was not present.
+    ///
+    /// The closure is only called in case the `key` does not already exist and may itself use this `Holder<T>`,
+    /// just like with [`insert_fn`](#method.insert_fn).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    ///
+    /// // load on first use.
+    /// for _ in 0..10 {
+    ///     let texture = holder.get_or_insert_with("player", || vec![0u8; 64]);
+    ///     assert_eq!(texture.len(), 64);
+    /// }
+    /// ```
+    ///
+    /// References stay valid while the closure inserts enough elements to grow the map:
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::with_capacity(1);
+    /// let first = holder.get_or_insert_with("first", || String::from("first"));
+    /// let capacity = holder.capacity();
+    ///
+    /// let level = holder.get_or_insert_with("level", || {
+    ///     let tiles: Vec<&String> = (0..64)
+    ///         .map(|i| holder.get_or_insert_with(&format!("tile_{}", i), || i.to_string()))
+    ///         .collect();
+    ///     assert_eq!(first, "first");
+    ///     tiles.iter().map(|tile| tile.as_str()).collect::<Vec<_>>().join(",")
+    /// });
+    ///
+    /// assert!(holder.capacity() > capacity);
+    /// assert_eq!(first, "first");
+    /// assert!(level.starts_with("0,1,2,"));
+    /// assert_eq!(holder.get("tile_63"), Some(&"63".to_string()));
+    /// ```
+    pub fn get_or_insert_with<Q, F>(&self, key: &Q, element: F) -> &T
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.get_or_insert_boxed_with(key, || Box::new(element()))
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an element created by
+    /// the closure in case the `key` was not present.
+    ///
+    /// Just like [`get_or_insert_with`](#method.get_or_insert_with), but also returns `true` in case the
+    /// element was freshly inserted and `false` in case it already existed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// assert_eq!(holder.get_or_insert_with_status("a", || 42), (&42, true));
+    /// assert_eq!(holder.get_or_insert_with_status("a", || 25), (&42, false));
+    /// ```
+    pub fn get_or_insert_with_status<Q, F>(&self, key: &Q, element: F) -> (&T, bool)
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.insert_with(key, || Ok::<_, Infallible>(Box::new(element()))) {
+            Ok(status) => status,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Removes the element corresponding to the key, returning it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// let id = holder.insert_id("a", 42);
+    /// assert_eq!(holder.remove("a"), Some(42));
+    /// assert_eq!(holder.remove("a"), None);
+    /// assert_eq!(holder.get_by_id(id), None);
+    /// ```
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.remove_boxed(key).map(|element| *element)
+    }
+
+    /// Inserts an `element` accessible by `key`, replacing and returning the old `element` in case
+    /// the `key` was already present.
+    ///
+    /// Unlike [`insert`](#method.insert), this method requires unique access to the `Holder<T>`.
+    /// The id of a replaced element stays the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// let id = holder.insert_id("a", 42);
+    /// assert_eq!(holder.replace("a", 25), Some(42));
+    /// assert_eq!(holder.replace("b", 7), None);
+    /// assert_eq!(holder.get_by_id(id), Some(&25));
+    /// ```
+    pub fn replace<Q>(&mut self, key: &Q, element: T) -> Option<T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        if let Some(existing) = self.get_mut(key) {
+            return Some(mem::replace(existing, element));
+        }
+
+        self.insert(key, element);
+        None
+    }
+
+    /// Clears the map, returning all `key`-`element` pairs as an iterator. Keeps the allocated memory for reuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    ///
+    /// let mut drained: Vec<(String, u32)> = holder.drain().collect();
+    /// drained.sort();
+    /// assert_eq!(drained, [("a".to_string(), 1), ("b".to_string(), 2)]);
+    /// assert!(holder.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, T, K> {
+        unsafe { &mut *self.ids.get() }.clear();
+        Drain {
+            inner: unsafe { &mut *self.items.get() }.drain(),
+        }
+    }
+}
+
+impl<T: ?Sized, K: Eq + Hash, S: BuildHasher + Default> Default for Holder<T, K, S> {
+    /// Creates an empty `Holder<T>` using the default hasher.
+    fn default() -> Self {
+        Holder::with_hasher(S::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, K: fmt::Debug + Eq + Hash, S: BuildHasher> fmt::Debug for Holder<T, K, S> {
+    /// Formats the `Holder<T>` as a map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    /// assert_eq!(format!("{:?}", holder), r#"{"a": 42}"#);
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone, K: Clone + Eq + Hash, S: BuildHasher + Clone> Clone for Holder<T, K, S> {
+    /// Clones the `Holder<T>` and all of its elements.
+    ///
+    /// The clone has its own ids, so ids of the original `Holder<T>` can not be used with the clone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// let id = holder.insert_id("a", 42);
+    ///
+    /// let clone = holder.clone();
+    /// assert_eq!(clone, holder);
+    /// assert_eq!(clone.get_by_id(id), None);
+    /// assert_eq!(clone.get_by_id(clone.id("a").unwrap()), Some(&42));
+    /// ```
+    fn clone(&self) -> Self {
+        let items = unsafe {& *self.items.get() };
+        let mut ids = Ids::with_capacity(items.len());
+        let mut cloned = HashMap::with_capacity_and_hasher(items.len(), items.hasher().clone());
+        cloned.extend(items.iter().map(|(key, slot)| {
+            let key = StableBox::new(key.get().clone());
+            let element = StableBox::new(slot.element.get().clone());
+            let slot = Slot::new(ids.insert(key.ptr, element.ptr), element);
+            (key, slot)
+        }));
+
+        Holder {
+            items: UnsafeCell::new(cloned),
+            ids: UnsafeCell::new(ids),
+            #[cfg(feature = "stats")]
+            counters: Counters::default(),
+        }
+    }
+}
+
+impl<T: ?Sized + PartialEq, K: Eq + Hash, S: BuildHasher> PartialEq for Holder<T, K, S> {
+    /// Two `Holder`s are equal in case they contain the same `key`-`element` pairs, ignoring ids.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let a = holder::Holder::new();
+    /// a.insert("a", 1);
+    /// a.insert("b", 2);
+    ///
+    /// let b = holder::Holder::new();
+    /// b.insert("b", 2);
+    /// assert_ne!(a, b);
+    /// b.insert("a", 1);
+    /// assert_eq!(a, b);
+    /// ```
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(key, element)| other.get(key) == Some(element))
+    }
+}
+
+impl<T: ?Sized + Eq, K: Eq + Hash, S: BuildHasher> Eq for Holder<T, K, S> {}
+
+impl<T: ?Sized, K, Q: ?Sized, S: BuildHasher> Index<&Q> for Holder<T, K, S>
+where K: Eq + Hash + Borrow<Q>, Q: Eq + Hash + fmt::Debug {
+    type Output = T;
+
+    /// Returns a reference to the element corresponding to the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics in case the `key` is not present in the `Holder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder["a"], 42);
+    /// ```
+    ///
+    /// ```should_panic
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::Holder<u32> = holder::Holder::new();
+    /// holder["player"]; // panics with: no element found for key "player"
+    /// ```
+    fn index(&self, key: &Q) -> &T {
+        match self.get(key) {
+            Some(element) => element,
+            None => panic!("no element found for key {:?}", key),
+        }
+    }
+}
+
+impl<T, K: Eq + Hash, S: BuildHasher + Default> FromIterator<(K, T)> for Holder<T, K, S> {
+    /// Creates a `Holder<T>` from an iterator of `key`-`element` pairs.
+    ///
+    /// Just like with `HashMap`, the last element of each key is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder::Holder;
+    ///
+    /// let holder: Holder<u32> = vec![("a".to_string(), 1), ("a".to_string(), 2)].into_iter().collect();
+    /// assert_eq!(holder.len(), 1);
+    /// assert_eq!(holder["a"], 2);
+    /// ```
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        let mut holder = Holder::default();
+        holder.extend(iter);
+        holder
+    }
+}
+
+impl<T, K: Eq + Hash, S: BuildHasher> Extend<(K, T)> for Holder<T, K, S> {
+    /// Inserts all `key`-`element` pairs of the iterator, replacing the elements of keys which are already present.
+    ///
+    /// This is consistent with `HashMap`, while extending a shared reference to a `Holder<T>` keeps existing elements instead.
+    /// The ids of replaced elements stay the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// let id = holder.insert_id("a", 1);
+    /// holder.extend(vec![("a".to_string(), 10), ("b".to_string(), 20)]);
+    /// assert_eq!(holder["a"], 10);
+    /// assert_eq!(holder["b"], 20);
+    /// assert_eq!(holder.get_by_id(id), Some(&10));
+    /// ```
+    fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        for (key, element) in iter {
+            match self.get_mut(&key) {
+                Some(existing) => *existing = element,
+                None => self.insert_owned(key, Box::new(element)),
+            }
+        }
+    }
+}
+
+impl<T, K: Eq + Hash, S: BuildHasher> Extend<(K, T)> for &Holder<T, K, S> {
+    /// Inserts all `key`-`element` pairs of the iterator, ignoring keys which are already present.
+    ///
+    /// This can be used while `Holder<T>` is already immutably borrowed, which is why existing elements are never replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// let a = holder.get_or_insert("a", 1);
+    /// (&holder).extend(vec![("b".to_string(), 2), ("c".to_string(), 3)]);
+    /// assert_eq!(*a, 1);
+    /// assert_eq!(holder.len(), 3);
+    /// ```
+    fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        for (key, element) in iter {
+            self.insert_owned(key, Box::new(element));
+        }
+    }
+}
+
+impl<T, K: Eq + Hash, S: BuildHasher> RetainMut<T> for Holder<T, K, S> {
+    /// Retains only the elements specified by the predicate, ignoring their keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    /// use crow_util::traits::RetainMut;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    /// holder.retain_mut(|x| { *x += 1; *x % 2 == 0 });
+    /// assert_eq!(holder.get("a"), Some(&2));
+    /// assert_eq!(holder.get("b"), None);
+    /// ```
+    fn retain_mut<F>(&mut self, mut f: F)
+        where F: FnMut(&mut T) -> bool
+    {
+        self.retain(|_, element| f(element));
+    }
+}
+
+impl<'a, T: ?Sized, K: Eq + Hash, S: BuildHasher> IntoIterator for &'a Holder<T, K, S> {
+    type Item = (&'a K, &'a T);
+    type IntoIter = Iter<'a, T, K>;
+
+    fn into_iter(self) -> Iter<'a, T, K> {
+        self.iter()
+    }
+}
+
+impl<'a, T: ?Sized, K: Eq + Hash, S: BuildHasher> IntoIterator for &'a mut Holder<T, K, S> {
+    type Item = (&'a K, &'a mut T);
+    type IntoIter = IterMut<'a, T, K>;
+
+    fn into_iter(self) -> IterMut<'a, T, K> {
+        self.iter_mut()
+    }
+}
+
+impl<T, K: Eq + Hash, S: BuildHasher> IntoIterator for Holder<T, K, S> {
+    type Item = (K, T);
+    type IntoIter = IntoIter<T, K>;
+
+    /// Creates a consuming iterator, visiting all `key`-`element` pairs in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 1);
+    ///
+    /// let pairs: Vec<(String, u32)> = holder.into_iter().collect();
+    /// assert_eq!(pairs, [("a".to_string(), 1)]);
+    /// ```
+    fn into_iter(self) -> IntoIter<T, K> {
+        IntoIter {
+            inner: self.items.into_inner().into_iter(),
+        }
+    }
+}
+
+/// A small handle to an element of a [`Holder<T>`](struct.Holder.html), allowing for lookups without hashing the key.
+///
+/// Ids are created by [`Holder::insert_id`](struct.Holder.html#method.insert_id) and [`Holder::id`](struct.Holder.html#method.id).
+/// Each id belongs to the `Holder<T>` which created it and is never reused, even after its element was removed.
+pub struct HolderId<T: ?Sized> {
+    tag: u64,
+    index: usize,
+    generation: u32,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: ?Sized> Clone for HolderId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for HolderId<T> {}
+
+impl<T: ?Sized> PartialEq for HolderId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag && self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T: ?Sized> Eq for HolderId<T> {}
+
+impl<T: ?Sized> Hash for HolderId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tag.hash(state);
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for HolderId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HolderId({}v{})", self.index, self.generation)
+    }
+}
+
+/// The source of the tags which distinguish the ids of different holders.
+static NEXT_TAG: AtomicU64 = AtomicU64::new(0);
+
+/// The elements of a `Holder<T>` indexed by their id, which is also used to iterate over them.
+///
+/// Indices of removed elements are reused, so each index has a generation which is incremented on removal.
+/// Clearing assigns a new tag, which invalidates all existing ids at once.
+struct Ids<T: ?Sized, K> {
+    tag: u64,
+    slots: Vec<IdSlot<T, K>>,
+    /// The indices of all empty slots which can still be reused.
+    free: Vec<usize>,
+    /// The number of elements inserted so far.
+    insertions: u64,
+}
+
+struct IdSlot<T: ?Sized, K> {
+    generation: u32,
+    /// The value of `Ids::insertions` before this element was inserted,
+    /// used to skip elements inserted after an iterator was created.
+    inserted: u64,
+    entry: Option<(NonNull<K>, NonNull<T>)>,
+}
+
+impl<T: ?Sized, K> Ids<T, K> {
+    fn with_capacity(capacity: usize) -> Self {
+        Ids {
+            tag: NEXT_TAG.fetch_add(1, Ordering::Relaxed),
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            insertions: 0,
+        }
+    }
+
+    /// Returns the id of the element at `index`.
+    fn id(&self, index: usize) -> HolderId<T> {
+        HolderId {
+            tag: self.tag,
+            index,
+            generation: self.slots[index].generation,
+            _marker: PhantomData,
+        }
+    }
+
+    fn get(&self, id: HolderId<T>) -> Option<NonNull<T>> {
+        if id.tag != self.tag {
+            return None;
+        }
+
+        self.slots.get(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.entry)
+            .map(|(_, element)| element)
+    }
+
+    /// Stores `key` and `element`, returning the index of the element.
+    fn insert(&mut self, key: NonNull<K>, element: NonNull<T>) -> usize {
+        let inserted = self.insertions;
+        self.insertions += 1;
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.inserted = inserted;
+                slot.entry = Some((key, element));
+                index
+            }
+            None => {
+                self.slots.push(IdSlot { generation: 0, inserted, entry: Some((key, element)) });
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        let slot = &mut self.slots[index];
+        slot.entry = None;
+        // a slot whose generation would overflow is never reused, so its old ids can not become valid again.
+        if slot.generation < u32::MAX {
+            slot.generation += 1;
+            self.free.push(index);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.tag = NEXT_TAG.fetch_add(1, Ordering::Relaxed);
+        self.slots.clear();
+        self.free.clear();
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.slots.shrink_to_fit();
+        self.free.shrink_to_fit();
+    }
+}
+
+/// A view into a single entry of a [`Holder<T>`](struct.Holder.html), which may either be vacant or occupied.
+///
+/// This enum is created by [`Holder::entry`](struct.Holder.html#method.entry).
+pub enum Entry<'a, T: ?Sized + 'a, K: 'a = String, S: 'a = RandomState> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, T, K, S>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, T, K>),
+}
+
+impl<'a, T: ?Sized, K, S> Entry<'a, T, K, S> {
+    /// Returns a reference to the key of this entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder: holder::Holder<u32> = holder::Holder::new();
+    /// assert_eq!(holder.entry("a".to_string()).key(), "a");
+    /// ```
+    pub fn key(&self) -> &K {
+        match *self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    /// Ensures an element is in the entry by inserting the already boxed `default` if empty,
+    /// returning a mutable reference to the element in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder: holder::Holder<str> = holder::Holder::new();
+    /// holder.entry("a".to_string()).or_insert_boxed("hello".into()).make_ascii_uppercase();
+    /// assert_eq!(holder.get("a"), Some("HELLO"));
+    /// ```
+    pub fn or_insert_boxed(self, default: Box<T>) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert_boxed(default),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.entry("a".to_string()).and_modify(|x| *x += 1).or_insert(42);
+    /// holder.entry("a".to_string()).and_modify(|x| *x += 1).or_insert(42);
+    /// assert_eq!(holder.get("a"), Some(&43));
+    /// ```
+    pub fn and_modify<F>(self, f: F) -> Self
+    where F: FnOnce(&mut T) {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, T, K, S> Entry<'a, T, K, S> {
+    /// Ensures an element is in the entry by inserting `default` if empty,
+    /// returning a mutable reference to the element in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// *holder.entry("a".to_string()).or_insert(42) += 1;
+    /// assert_eq!(holder.get("a"), Some(&43));
+    /// ```
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        self.or_insert_with(|| default)
+    }
+
+    /// Ensures an element is in the entry by inserting the result of `default` if empty,
+    /// returning a mutable reference to the element in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.entry("a".to_string()).or_insert_with(Vec::new).push(42);
+    /// assert_eq!(holder.get("a"), Some(&vec![42]));
+    /// ```
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut T
+    where F: FnOnce() -> T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures an element is in the entry by inserting the default value if empty,
+    /// returning a mutable reference to the element in the entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder: holder::Holder<u32> = holder::Holder::new();
+    /// *holder.entry("a".to_string()).or_default() += 7;
+    /// assert_eq!(holder.get("a"), Some(&7));
+    /// ```
+    pub fn or_default(self) -> &'a mut T
+    where T: Default {
+        self.or_insert_with(T::default)
+    }
+}
+
+/// A view into an occupied entry of a [`Holder<T>`](struct.Holder.html). It is part of the [`Entry`](enum.Entry.html) enum.
+pub struct OccupiedEntry<'a, T: ?Sized + 'a, K: 'a = String, S: 'a = RandomState> {
+    items: &'a mut HashMap<StableBox<K>, Slot<T>, S>,
+    ids: &'a mut Ids<T, K>,
+    key: NonNull<K>,
+    element: NonNull<T>,
+}
+
+impl<'a, T: ?Sized, K, S> OccupiedEntry<'a, T, K, S> {
+    /// Returns a reference to the key of this entry.
+    pub fn key(&self) -> &K {
+        unsafe { &*self.key.as_ptr() }
+    }
+
+    /// Returns a reference to the element in this entry.
+    pub fn get(&self) -> &T {
+        unsafe { &*self.element.as_ptr() }
+    }
+
+    /// Returns a mutable reference to the element in this entry.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.element.as_ptr() }
+    }
+
+    /// Converts the entry into a mutable reference to its element, which lives as long as the `Holder<T>` is borrowed.
+    pub fn into_mut(self) -> &'a mut T {
+        unsafe { &mut *self.element.as_ptr() }
+    }
+
+    /// Removes the entry from the `Holder<T>`, returning its key and its element as a `Box<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder: holder::Holder<str> = holder::Holder::new();
+    /// holder.insert_boxed("a", "hello".into());
+    ///
+    /// if let holder::Entry::Occupied(entry) = holder.entry("a".to_string()) {
+    ///     assert_eq!(entry.remove_boxed(), ("a".to_string(), "hello".into()));
+    /// }
+    /// assert!(holder.is_empty());
+    /// ```
+    pub fn remove_boxed(self) -> (K, Box<T>)
+    where K: Eq + Hash, S: BuildHasher {
+        // the key is boxed, so it stays at the same address while it is removed from `items`.
+        let (key, slot) = self.items.remove_entry(Query::new(unsafe { &*self.key.as_ptr() })).unwrap();
+        self.ids.remove(slot.id);
+        (key.into_inner(), slot.element.into_box())
+    }
+}
+
+impl<'a, T, K, S> OccupiedEntry<'a, T, K, S> {
+    /// Replaces the element in this entry, returning the old element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    ///
+    /// if let holder::Entry::Occupied(mut entry) = holder.entry("a".to_string()) {
+    ///     assert_eq!(entry.insert(25), 42);
+    /// }
+    /// assert_eq!(holder.get("a"), Some(&25));
+    /// ```
+    pub fn insert(&mut self, element: T) -> T {
+        mem::replace(self.get_mut(), element)
+    }
+
+    /// Removes the entry from the `Holder<T>`, returning its element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    ///
+    /// if let holder::Entry::Occupied(entry) = holder.entry("a".to_string()) {
+    ///     assert_eq!(entry.remove(), 42);
+    /// }
+    /// assert!(holder.is_empty());
+    /// ```
+    pub fn remove(self) -> T
+    where K: Eq + Hash, S: BuildHasher {
+        *self.remove_boxed().1
+    }
+}
+
+/// A view into a vacant entry of a [`Holder<T>`](struct.Holder.html). It is part of the [`Entry`](enum.Entry.html) enum.
+pub struct VacantEntry<'a, T: ?Sized + 'a, K: 'a = String> {
+    inner: hash_map::VacantEntry<'a, StableBox<K>, Slot<T>>,
+    ids: &'a mut Ids<T, K>,
+}
+
+impl<'a, T: ?Sized, K> VacantEntry<'a, T, K> {
+    /// Returns a reference to the key of this entry.
+    pub fn key(&self) -> &K {
+        self.inner.key().get()
+    }
+
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> K {
+        self.inner.into_key().into_inner()
+    }
+
+    /// Inserts the already boxed `element` into the `Holder<T>`, returning a mutable reference to it.
+    pub fn insert_boxed(self, element: Box<T>) -> &'a mut T {
+        let element = StableBox::from_box(element);
+        let slot = Slot::new(self.ids.insert(self.inner.key().ptr, element.ptr), element);
+        self.inner.insert(slot).element.get_mut()
+    }
+}
+
+impl<'a, T, K> VacantEntry<'a, T, K> {
+    /// Inserts the `element` into the `Holder<T>`, returning a mutable reference to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::Holder::new();
+    ///
+    /// if let holder::Entry::Vacant(entry) = holder.entry("a".to_string()) {
+    ///     *entry.insert(42) += 1;
+    /// }
+    /// assert_eq!(holder.get("a"), Some(&43));
+    /// ```
+    pub fn insert(self, element: T) -> &'a mut T {
+        self.insert_boxed(Box::new(element))
+    }
+}
+
+/// An iterator over the `key`-`element` pairs of a [`Holder<T>`](struct.Holder.html).
+///
+/// This struct is created by [`Holder::iter`](struct.Holder.html#method.iter).
+pub struct Iter<'a, T: ?Sized + 'a, K: 'a = String> {
+    ids: *const Ids<T, K>,
+    index: usize,
+    /// Elements inserted after this iterator was created are skipped.
+    insertions: u64,
+    remaining: usize,
+    _marker: PhantomData<(&'a K, &'a T)>,
+}
+
+impl<'a, T: ?Sized, K> Iterator for Iter<'a, T, K> {
+    type Item = (&'a K, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the ids are only borrowed during this call, as the holder may be modified between calls.
+        let ids = unsafe { &*self.ids };
+        while self.remaining > 0 {
+            let slot = &ids.slots[self.index];
+            self.index += 1;
+            if let Some((key, element)) = slot.entry {
+                if slot.inserted < self.insertions {
+                    self.remaining -= 1;
+                    return Some(unsafe { (&*key.as_ptr(), &*element.as_ptr()) });
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T: ?Sized, K> ExactSizeIterator for Iter<'a, T, K> {}
+
+/// An iterator over the keys of a [`Holder<T>`](struct.Holder.html).
+///
+/// This struct is created by [`Holder::keys`](struct.Holder.html#method.keys).
+pub struct Keys<'a, T: ?Sized + 'a, K: 'a = String> {
+    inner: Iter<'a, T, K>,
+}
+
+impl<'a, T: ?Sized, K> Iterator for Keys<'a, T, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: ?Sized, K> ExactSizeIterator for Keys<'a, T, K> {}
+
+/// An iterator over the elements of a [`Holder<T>`](struct.Holder.html).
+///
+/// This struct is created by [`Holder::values`](struct.Holder.html#method.values).
+pub struct Values<'a, T: ?Sized + 'a, K: 'a = String> {
+    inner: Iter<'a, T, K>,
+}
+
+impl<'a, T: ?Sized, K> Iterator for Values<'a, T, K> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: ?Sized, K> ExactSizeIterator for Values<'a, T, K> {}
+
+/// A mutable iterator over the `key`-`element` pairs of a [`Holder<T>`](struct.Holder.html).
+///
+/// This struct is created by [`Holder::iter_mut`](struct.Holder.html#method.iter_mut).
+pub struct IterMut<'a, T: ?Sized + 'a, K: 'a = String> {
+    inner: hash_map::IterMut<'a, StableBox<K>, Slot<T>>,
+}
+
+impl<'a, T: ?Sized, K> Iterator for IterMut<'a, T, K> {
+    type Item = (&'a K, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, slot)| (k.get(), slot.element.get_mut()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: ?Sized, K> ExactSizeIterator for IterMut<'a, T, K> {}
+
+/// A draining iterator over the `key`-`element` pairs of a [`Holder<T>`](struct.Holder.html).
+///
+/// This struct is created by [`Holder::drain`](struct.Holder.html#method.drain).
+pub struct Drain<'a, T: 'a, K: 'a = String> {
+    inner: hash_map::Drain<'a, StableBox<K>, Slot<T>>,
+}
+
+impl<'a, T, K> Iterator for Drain<'a, T, K> {
+    type Item = (K, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, slot)| (k.into_inner(), slot.element.into_inner()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, K> ExactSizeIterator for Drain<'a, T, K> {}
+
+/// An owning iterator over the `key`-`element` pairs of a [`Holder<T>`](struct.Holder.html).
+///
+/// This struct is created by the `into_iter` method of [`Holder<T>`](struct.Holder.html).
+pub struct IntoIter<T, K = String> {
+    inner: hash_map::IntoIter<StableBox<K>, Slot<T>>,
+}
+
+impl<T, K> Iterator for IntoIter<T, K> {
+    type Item = (K, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, slot)| (k.into_inner(), slot.element.into_inner()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, K> ExactSizeIterator for IntoIter<T, K> {}
+
+/// A value stored in its own allocation, meaning that references to it stay valid while the map is rehashed.
+///
+/// A raw pointer is used instead of a `Box<T>`, as moving a `Box` asserts unique access to its content.
+struct StableBox<T: ?Sized> {
+    ptr: NonNull<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for StableBox<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for StableBox<T> {}
+
+impl<T> StableBox<T> {
+    fn new(value: T) -> Self {
+        StableBox::from_box(Box::new(value))
+    }
+
+    fn into_inner(self) -> T {
+        *self.into_box()
+    }
+}
+
+impl<T: ?Sized> StableBox<T> {
+    fn from_box(value: Box<T>) -> Self {
+        StableBox {
+            ptr: unsafe { NonNull::new_unchecked(Box::into_raw(value)) },
+        }
+    }
+
+    fn get(&self) -> &T {
+        unsafe { &*self.ptr.as_ptr() }
+    }
+
+    fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.ptr.as_ptr() }
+    }
+
+    fn into_box(self) -> Box<T> {
+        let value = unsafe { Box::from_raw(self.ptr.as_ptr()) };
+        ::std::mem::forget(self);
+        value
+    }
+}
+
+impl<T: ?Sized> Drop for StableBox<T> {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.ptr.as_ptr())) }
+    }
+}
+
+impl<T: ?Sized + Hash> Hash for StableBox<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get().hash(state)
+    }
+}
+
+impl<T: ?Sized + PartialEq> PartialEq for StableBox<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for StableBox<T> {}
+
+/// An element of a `Holder<T>` together with its id.
+struct Slot<T: ?Sized> {
+    id: usize,
+    element: StableBox<T>,
+    /// The number of successful lookups of this element.
+    #[cfg(feature = "stats")]
+    hits: Cell<usize>,
+}
+
+impl<T: ?Sized> Slot<T> {
+    fn new(id: usize, element: StableBox<T>) -> Self {
+        Slot {
+            id,
+            element,
+            #[cfg(feature = "stats")]
+            hits: Cell::new(0),
+        }
+    }
+}
+
+/// A borrowed form of a key, used to look up a `StableBox<K>` by any `Q` with `K: Borrow<Q>`.
+#[repr(transparent)]
+struct Query<Q: ?Sized> {
+    key: Q,
+}
+
+impl<Q: ?Sized> Query<Q> {
+    fn new(key: &Q) -> &Query<Q> {
+        // `Query<Q>` is `repr(transparent)`, so this cast is valid.
+        unsafe { &*(key as *const Q as *const Query<Q>) }
+    }
+}
+
+impl<Q: ?Sized + Hash> Hash for Query<Q> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
+impl<Q: ?Sized + PartialEq> PartialEq for Query<Q> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<Q: ?Sized + Eq> Eq for Query<Q> {}
+
+impl<K: Borrow<Q>, Q: ?Sized> Borrow<Query<Q>> for StableBox<K> {
+    fn borrow(&self) -> &Query<Q> {
+        Query::new(self.get().borrow())
+    }
 }
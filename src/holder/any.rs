@@ -0,0 +1,222 @@
+//! A registry containing one `Holder<T>` for each type.
+use std::any::{self, Any, TypeId};
+
+use super::Holder;
+
+/// A type-erased map which stores elements of any type, keyed by their type together with their name.
+///
+/// Elements of different types can have the same name, and a lookup with the wrong type simply misses.
+/// Internally, every type has its own [`Holder<T>`](struct.Holder.html), which is created when the first element
+/// of that type is inserted. Just like with `Holder<T>`, references to elements stay valid while new elements are inserted.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// struct Texture(u32);
+/// struct Sound(&'static str);
+///
+/// let assets = holder::AnyHolder::new();
+/// let texture = assets.get_or_insert_with("player", || Texture(7));
+/// assets.insert("player", Sound("footsteps"));
+///
+/// assert_eq!(texture.0, 7);
+/// assert_eq!(assets.get::<Sound>("player").unwrap().0, "footsteps");
+/// assert!(assets.get::<u32>("player").is_none());
+/// ```
+pub struct AnyHolder {
+    holders: Holder<dyn TypedHolder, TypeId>,
+}
+
+impl AnyHolder {
+    /// Constructs a new, empty `AnyHolder`.
+    pub fn new() -> Self {
+        AnyHolder {
+            holders: Holder::new(),
+        }
+    }
+
+    /// Returns a reference to the element of type `T` corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::AnyHolder::new();
+    /// holder.insert("answer", 42_u32);
+    /// assert_eq!(holder.get::<u32>("answer"), Some(&42));
+    /// assert_eq!(holder.get::<i32>("answer"), None);
+    /// ```
+    pub fn get<T: ?Sized + 'static>(&self, key: &str) -> Option<&T> {
+        self.typed::<T>().and_then(|holder| holder.get(key))
+    }
+
+    /// Inserts an `element` of type `T` accessible by `key`.
+    ///
+    /// In case the `key` was already present for type `T`, the old `element` is returned and the new one is ignored.
+    /// This method can be used while `AnyHolder` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::AnyHolder::new();
+    /// assert_eq!(holder.insert("a", 42), None);
+    /// assert_eq!(holder.insert("a", 25), Some(&42));
+    /// assert_eq!(holder.insert("a", 'c'), None);
+    /// ```
+    pub fn insert<T: 'static>(&self, key: &str, element: T) -> Option<&T> {
+        self.holder::<T>().insert(key, element)
+    }
+
+    /// Inserts an `element` of type `T`, which is created by a closure and can be accessed by `key`.
+    ///
+    /// In case the `key` was already present for type `T`, the old `element` is returned and the closure is not called.
+    /// Just like with [`Holder::insert_fn`](struct.Holder.html#method.insert_fn), the closure itself may use this `AnyHolder`.
+    pub fn insert_fn<T: 'static, F>(&self, key: &str, element: F) -> Option<&T>
+    where F: FnOnce() -> T {
+        self.holder::<T>().insert_fn(key, element)
+    }
+
+    /// Returns a reference to the element of type `T` corresponding to `key`, inserting an element created by
+    /// the closure in case the `key` was not present for type `T`.
+    pub fn get_or_insert_with<T: 'static, F>(&self, key: &str, element: F) -> &T
+    where F: FnOnce() -> T {
+        self.holder::<T>().get_or_insert_with(key, element)
+    }
+
+    /// Returns a reference to the `Holder<T>` containing all elements of type `T`, creating it in case it does not exist.
+    ///
+    /// This can be used to pass the elements of a single type to code which only works with a `Holder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::AnyHolder::new();
+    /// let sounds = holder.holder::<&str>();
+    /// sounds.insert("step", "footsteps.wav");
+    ///
+    /// assert_eq!(holder.get::<&str>("step"), Some(&"footsteps.wav"));
+    /// ```
+    pub fn holder<T: ?Sized + 'static>(&self) -> &Holder<T> {
+        let holder = self.holders.get_or_insert_boxed_with(&TypeId::of::<T>(), || Box::new(Holder::<T>::new()));
+        downcast_ref(holder)
+    }
+
+    /// Returns a mutable reference to the `Holder<T>` containing all elements of type `T`, in case it exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::AnyHolder::new();
+    /// holder.insert("a", 42_u32);
+    ///
+    /// holder.holder_mut::<u32>().unwrap().remove("a");
+    /// assert_eq!(holder.get::<u32>("a"), None);
+    /// assert!(holder.holder_mut::<i32>().is_none());
+    /// ```
+    pub fn holder_mut<T: ?Sized + 'static>(&mut self) -> Option<&mut Holder<T>> {
+        self.holders.get_mut(&TypeId::of::<T>())
+            .map(|holder| holder.as_any_mut().downcast_mut::<Holder<T>>().unwrap())
+    }
+
+    /// Returns the names of all types which have a `Holder<T>`, in arbitrary order.
+    ///
+    /// The names are created by `std::any::type_name` and should only be used for debugging.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::AnyHolder::new();
+    /// holder.insert("a", 42_u32);
+    /// holder.insert("b", "text");
+    ///
+    /// let mut types = holder.types();
+    /// types.sort();
+    /// assert_eq!(types, ["&str", "u32"]);
+    /// ```
+    pub fn types(&self) -> Vec<&'static str> {
+        self.holders.values().map(|holder| holder.type_name()).collect()
+    }
+
+    /// Clears the map, removing all elements of every type.
+    pub fn clear(&mut self) {
+        self.holders.clear();
+    }
+
+    /// Returns the number of elements of every type in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::AnyHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.insert("a", "text");
+    /// assert_eq!(holder.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.holders.values().map(|holder| holder.len()).sum()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the `Holder<T>` of type `T` without creating it.
+    fn typed<T: ?Sized + 'static>(&self) -> Option<&Holder<T>> {
+        self.holders.get(&TypeId::of::<T>()).map(downcast_ref)
+    }
+}
+
+impl Default for AnyHolder {
+    /// Creates an empty `AnyHolder`.
+    fn default() -> Self {
+        AnyHolder::new()
+    }
+}
+
+/// A `Holder<T>` whose element type was erased.
+trait TypedHolder: Any {
+    fn type_name(&self) -> &'static str;
+
+    fn len(&self) -> usize;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: ?Sized + 'static> TypedHolder for Holder<T> {
+    fn type_name(&self) -> &'static str {
+        any::type_name::<T>()
+    }
+
+    fn len(&self) -> usize {
+        Holder::len(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Converts `holder` back into a `Holder<T>`, which must be stored at `TypeId::of::<T>()`.
+fn downcast_ref<T: ?Sized + 'static>(holder: &dyn TypedHolder) -> &Holder<T> {
+    holder.as_any().downcast_ref::<Holder<T>>().unwrap()
+}
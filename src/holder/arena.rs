@@ -0,0 +1,167 @@
+//! A version of `Holder<T>` which stores its elements in chunks instead of separate boxes.
+use std::borrow::Borrow;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ptr::NonNull;
+
+/// The capacity of the first chunk in case no capacity was specified.
+const MIN_CHUNK_CAPACITY: usize = 16;
+
+/// A [`Holder<T>`](struct.Holder.html) which stores its elements in chunks that never move, instead of one `Box` per element.
+///
+/// `Holder<T>` needs one allocation per element to keep references valid while inserting new elements.
+/// `ArenaHolder<T>` instead moves each element into the last chunk, and adds a chunk twice as large once it is full.
+/// This greatly reduces the number of allocations and improves cache locality for many small elements.
+/// Elements can not be removed individually, only [`clear`](#method.clear) frees the chunks.
+///
+/// Apart from that, the methods behave just like the ones of `Holder<T>`.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let holder = holder::ArenaHolder::new();
+/// let a = holder.get_or_insert_with("a", || 7);
+///
+/// for i in 0..1000 {
+///     holder.insert(&format!("sprite_{}", i), i);
+/// }
+///
+/// // `a` is still valid, although many larger chunks were added since.
+/// assert_eq!(a, &7);
+/// assert_eq!(holder.insert("a", 8), Some(&7));
+/// assert_eq!(holder.get("sprite_42"), Some(&42));
+/// assert_eq!(holder.len(), 1001);
+/// ```
+pub struct ArenaHolder<T, K = String> {
+    items: UnsafeCell<HashMap<K, NonNull<T>>>,
+    chunks: UnsafeCell<Vec<Vec<T>>>,
+}
+
+unsafe impl<T: Send, K: Send> Send for ArenaHolder<T, K> {}
+
+impl<T, K: Eq + Hash> ArenaHolder<T, K> {
+    /// Constructs a new, empty `ArenaHolder<T>`.
+    pub fn new() -> Self {
+        ArenaHolder {
+            items: UnsafeCell::new(HashMap::new()),
+            chunks: UnsafeCell::new(Vec::new()),
+        }
+    }
+
+    /// Constructs a new, empty `ArenaHolder<T>` which is able to hold `capacity` elements in its first chunk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::ArenaHolder::with_capacity(1000);
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder.get("a"), Some(&42));
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        ArenaHolder {
+            items: UnsafeCell::new(HashMap::with_capacity(capacity)),
+            chunks: UnsafeCell::new(vec![Vec::with_capacity(capacity.max(MIN_CHUNK_CAPACITY))]),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to the key.
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let items = unsafe {& *self.items.get() };
+        items.get(key).map(|ptr| unsafe { &*ptr.as_ptr() })
+    }
+
+    /// Inserts an `element` accessible by `key`.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the new one is ignored.
+    /// This method can be used while `ArenaHolder<T>` is already immutably borrowed.
+    pub fn insert<Q>(&self, key: &Q, element: T) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.insert_full(key, element) {
+            (existing, false) => Some(existing),
+            (_, true) => None,
+        }
+    }
+
+    /// Inserts an `element`, which is created by a closure and can be accessed by `key`.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the closure is not called.
+    /// Just like with [`Holder::insert_fn`](struct.Holder.html#method.insert_fn), the closure itself may use this `ArenaHolder<T>`.
+    pub fn insert_fn<Q, F>(&self, key: &Q, element: F) -> Option<&T>
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.get(key) {
+            Some(existing) => Some(existing),
+            None => self.insert(key, element()),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an element created by
+    /// the closure in case the `key` was not present.
+    pub fn get_or_insert_with<Q, F>(&self, key: &Q, element: F) -> &T
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.get(key) {
+            Some(existing) => existing,
+            None => self.insert_full(key, element()).0,
+        }
+    }
+
+    /// Clears the map, removing all `key`-`element` pairs.
+    pub fn clear(&mut self) {
+        self.items.get_mut().clear();
+        self.chunks.get_mut().clear();
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        unsafe { & *self.items.get() }.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        unsafe { & *self.items.get() }.is_empty()
+    }
+
+    /// Inserts `element` in case `key` is not already present.
+    ///
+    /// Returns a reference to the element corresponding to `key` and whether `element` was inserted.
+    fn insert_full<Q>(&self, key: &Q, element: T) -> (&T, bool)
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        if let Some(existing) = self.get(key) {
+            return (existing, false);
+        }
+
+        let ptr = self.alloc(element);
+        let items = unsafe {&mut *self.items.get() };
+        items.insert(key.to_owned(), ptr);
+        (unsafe { &*ptr.as_ptr() }, true)
+    }
+
+    /// Moves `element` into the last chunk, adding a new chunk in case the last one is full.
+    fn alloc(&self, element: T) -> NonNull<T> {
+        let chunks = unsafe {&mut *self.chunks.get() };
+        let full = chunks.last().is_none_or(|chunk| chunk.len() == chunk.capacity());
+        if full {
+            let capacity = chunks.last().map_or(MIN_CHUNK_CAPACITY, |chunk| chunk.capacity() * 2);
+            chunks.push(Vec::with_capacity(capacity));
+        }
+
+        // the chunk has enough capacity, so pushing does not move the existing elements.
+        let chunk = chunks.last_mut().unwrap();
+        chunk.push(element);
+        unsafe { NonNull::new_unchecked(chunk.as_mut_ptr().add(chunk.len() - 1)) }
+    }
+}
+
+impl<T, K: Eq + Hash> Default for ArenaHolder<T, K> {
+    /// Creates an empty `ArenaHolder<T>`.
+    fn default() -> Self {
+        ArenaHolder::new()
+    }
+}
@@ -0,0 +1,372 @@
+//! A version of `Holder<T>` which counts the handles of each element, allowing unused elements to be dropped.
+use std::borrow::Borrow;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+
+/// A map which counts the living handles of each element, so elements which are no longer used can be dropped.
+///
+/// Besides borrowing elements using [`get`](#method.get), owned [`HolderRc<T>`](struct.HolderRc.html) handles can be created
+/// using [`get_handle`](#method.get_handle). Each element counts its living handles, and
+/// [`purge_unused`](#method.purge_unused) drops all elements without any handles.
+/// This is useful for level streaming, where each level holds handles to its assets and unused assets are dropped once the level is unloaded.
+///
+/// Each element is stored in a single allocation together with its count, which is shared with its handles.
+/// Inserting and borrowing elements works just like with [`Holder<T>`](struct.Holder.html).
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let mut holder = holder::CountedHolder::new();
+/// holder.insert("player", "player texture");
+/// holder.insert("level_1", "level 1 tileset");
+/// holder.insert("level_2", "level 2 tileset");
+///
+/// let player = holder.get_handle("player").unwrap();
+/// let level = vec![holder.get_handle("level_1").unwrap()];
+/// assert_eq!(holder.purge_unused(), ["level_2"]);
+///
+/// drop(level);
+/// assert_eq!(holder.purge_unused(), ["level_1"]);
+/// assert_eq!(*player, "player texture");
+/// assert_eq!(holder.len(), 1);
+/// ```
+///
+/// Just like `Holder<T>`, a `CountedHolder<T>` can be moved to another thread, while its handles can be shared between threads:
+///
+/// ```
+/// use crow_util::holder;
+/// use std::thread;
+///
+/// let holder = holder::CountedHolder::new();
+/// holder.insert("a", 42);
+/// let handle = holder.get_handle("a").unwrap();
+///
+/// let mut holder = thread::spawn(move || {
+///     assert_eq!(holder.handles("a"), Some(1));
+///     holder
+/// }).join().unwrap();
+///
+/// thread::spawn(move || assert_eq!(*handle, 42)).join().unwrap();
+/// assert_eq!(holder.purge_unused(), ["a"]);
+/// ```
+pub struct CountedHolder<T, K = String> {
+    items: UnsafeCell<HashMap<K, NonNull<Counted<T>>>>,
+}
+
+unsafe impl<T: Send + Sync, K: Send> Send for CountedHolder<T, K> {}
+
+impl<T, K: Eq + Hash> CountedHolder<T, K> {
+    /// Constructs a new, empty `CountedHolder<T>`.
+    pub fn new() -> Self {
+        CountedHolder {
+            items: UnsafeCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to the key.
+    ///
+    /// Borrowing an element does not count as a handle, as the borrow checker already prevents
+    /// [`purge_unused`](#method.purge_unused) while the element is borrowed.
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.counted(key).map(|counted| &counted.element)
+    }
+
+    /// Returns a new handle to the element corresponding to the key, which keeps the element alive
+    /// during [`purge_unused`](#method.purge_unused) until it is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::CountedHolder::new();
+    /// holder.insert("a", 42);
+    ///
+    /// let handle = holder.get_handle("a").unwrap();
+    /// assert_eq!(*handle, 42);
+    /// assert_eq!(holder.handles("a"), Some(1));
+    ///
+    /// drop(handle);
+    /// assert_eq!(holder.handles("a"), Some(0));
+    /// assert!(holder.get_handle("b").is_none());
+    /// ```
+    pub fn get_handle<Q>(&self, key: &Q) -> Option<HolderRc<T>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.ptr(key).map(|ptr| {
+            unsafe { ptr.as_ref() }.count.fetch_add(1, Ordering::Relaxed);
+            HolderRc { ptr }
+        })
+    }
+
+    /// Returns the number of living handles to the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::CountedHolder::new();
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder.handles("a"), Some(0));
+    ///
+    /// let handle = holder.get_handle("a").unwrap();
+    /// let copy = handle.clone();
+    /// assert_eq!(holder.handles("a"), Some(2));
+    /// assert_eq!(holder.handles("b"), None);
+    /// ```
+    pub fn handles<Q>(&self, key: &Q) -> Option<usize>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        // the holder itself counts as one owner of each element.
+        self.counted(key).map(|counted| counted.count.load(Ordering::Acquire) - 1)
+    }
+
+    /// Inserts an `element` accessible by `key`.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the new one is ignored.
+    /// This method can be used while `CountedHolder<T>` is already immutably borrowed.
+    pub fn insert<Q>(&self, key: &Q, element: T) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.get(key) {
+            Some(existing) => Some(existing),
+            None => {
+                self.insert_new(key, element);
+                None
+            }
+        }
+    }
+
+    /// Inserts an `element`, which is created by a closure and can be accessed by `key`.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the closure is not called.
+    /// Just like with [`Holder::insert_fn`](struct.Holder.html#method.insert_fn), the closure itself may use this `CountedHolder<T>`.
+    pub fn insert_fn<Q, F>(&self, key: &Q, element: F) -> Option<&T>
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.get(key) {
+            Some(existing) => Some(existing),
+            None => self.insert(key, element()),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an element created by
+    /// the closure in case the `key` was not present.
+    pub fn get_or_insert_with<Q, F>(&self, key: &Q, element: F) -> &T
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        if let Some(existing) = self.get(key) {
+            return existing;
+        }
+
+        let element = element();
+        // the closure may have inserted `key` itself, in which case its element is kept.
+        match self.get(key) {
+            Some(existing) => existing,
+            None => self.insert_new(key, element),
+        }
+    }
+
+    /// Drops all elements without living handles, returning their keys in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::CountedHolder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    ///
+    /// let handle = holder.get_handle("a").unwrap();
+    /// assert_eq!(holder.purge_unused(), ["b"]);
+    /// assert!(holder.purge_unused().is_empty());
+    ///
+    /// drop(handle);
+    /// assert_eq!(holder.purge_unused(), ["a"]);
+    /// assert!(holder.is_empty());
+    /// ```
+    pub fn purge_unused(&mut self) -> Vec<K> {
+        // no handles can be created while `self` is mutably borrowed, so a count of 1 can not increase again.
+        self.items.get_mut()
+            .extract_if(|_, ptr| unsafe { ptr.as_ref() }.count.load(Ordering::Acquire) == 1)
+            .map(|(key, ptr)| {
+                unsafe { release(ptr) };
+                key
+            })
+            .collect()
+    }
+
+    /// Clears the map, removing all elements. Elements with living handles are dropped once their last handle is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::CountedHolder::new();
+    /// holder.insert("a", 42);
+    ///
+    /// let handle = holder.get_handle("a").unwrap();
+    /// holder.clear();
+    /// assert!(holder.is_empty());
+    /// assert_eq!(*handle, 42);
+    /// ```
+    pub fn clear(&mut self) {
+        for (_, ptr) in self.items.get_mut().drain() {
+            unsafe { release(ptr) };
+        }
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        unsafe { & *self.items.get() }.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        unsafe { & *self.items.get() }.is_empty()
+    }
+
+    fn ptr<Q>(&self, key: &Q) -> Option<NonNull<Counted<T>>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let items = unsafe {& *self.items.get() };
+        items.get(key).cloned()
+    }
+
+    fn counted<Q>(&self, key: &Q) -> Option<&Counted<T>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.ptr(key).map(|ptr| unsafe { &*ptr.as_ptr() })
+    }
+
+    /// Inserts `element` at `key`, which must not be present.
+    fn insert_new<Q>(&self, key: &Q, element: T) -> &T
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        let counted = Box::new(Counted {
+            count: AtomicUsize::new(1),
+            element,
+        });
+        let ptr = unsafe { NonNull::new_unchecked(Box::into_raw(counted)) };
+        let items = unsafe {&mut *self.items.get() };
+        items.insert(key.to_owned(), ptr);
+        unsafe { &(*ptr.as_ptr()).element }
+    }
+}
+
+impl<T, K: Eq + Hash> Default for CountedHolder<T, K> {
+    /// Creates an empty `CountedHolder<T>`.
+    fn default() -> Self {
+        CountedHolder::new()
+    }
+}
+
+impl<T, K> Drop for CountedHolder<T, K> {
+    fn drop(&mut self) {
+        for (_, ptr) in self.items.get_mut().drain() {
+            unsafe { release(ptr) };
+        }
+    }
+}
+
+impl<T: fmt::Debug, K: fmt::Debug + Eq + Hash> fmt::Debug for CountedHolder<T, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let items = unsafe { & *self.items.get() };
+        f.debug_map().entries(items.iter().map(|(key, ptr)| (key, unsafe { &ptr.as_ref().element }))).finish()
+    }
+}
+
+/// An element of a `CountedHolder<T>` together with the number of its owners,
+/// which are the holder itself and all handles.
+struct Counted<T> {
+    count: AtomicUsize,
+    element: T,
+}
+
+/// Gives up one ownership of `ptr`, dropping the element in case it was the last one.
+unsafe fn release<T>(ptr: NonNull<Counted<T>>) {
+    if ptr.as_ref().count.fetch_sub(1, Ordering::Release) == 1 {
+        // makes sure that all uses of the element by other owners happen before it is dropped.
+        atomic::fence(Ordering::Acquire);
+        drop(Box::from_raw(ptr.as_ptr()));
+    }
+}
+
+/// An owned handle to an element of a [`CountedHolder<T>`](struct.CountedHolder.html).
+///
+/// While a handle exists, its element is not dropped by [`CountedHolder::purge_unused`](struct.CountedHolder.html#method.purge_unused).
+/// Cloning a handle increments the count of its element, dropping it decrements the count again.
+/// The count is stored next to the element, so handles do not need an allocation of their own.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let holder = holder::CountedHolder::new();
+/// holder.insert("a", vec![1, 2, 3]);
+///
+/// let handle = holder.get_handle("a").unwrap();
+/// assert_eq!(handle.len(), 3);
+/// ```
+pub struct HolderRc<T> {
+    ptr: NonNull<Counted<T>>,
+}
+
+unsafe impl<T: Send + Sync> Send for HolderRc<T> {}
+unsafe impl<T: Send + Sync> Sync for HolderRc<T> {}
+
+impl<T> HolderRc<T> {
+    /// Returns `true` if both handles refer to the same element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder::{self, HolderRc};
+    ///
+    /// let holder = holder::CountedHolder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 1);
+    ///
+    /// let a = holder.get_handle("a").unwrap();
+    /// assert!(HolderRc::ptr_eq(&a, &holder.get_handle("a").unwrap()));
+    /// assert!(!HolderRc::ptr_eq(&a, &holder.get_handle("b").unwrap()));
+    /// ```
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+}
+
+impl<T> Clone for HolderRc<T> {
+    fn clone(&self) -> Self {
+        unsafe { self.ptr.as_ref() }.count.fetch_add(1, Ordering::Relaxed);
+        HolderRc {
+            ptr: self.ptr,
+        }
+    }
+}
+
+impl<T> Drop for HolderRc<T> {
+    fn drop(&mut self) {
+        unsafe { release(self.ptr) };
+    }
+}
+
+impl<T> Deref for HolderRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &self.ptr.as_ref().element }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for HolderRc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,458 @@
+//! An immutable version of `Holder<T>` using a perfect hash table, which can be shared between threads.
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::Index;
+use std::slice;
+
+use super::Holder;
+
+/// The average number of keys per bucket, a larger value reduces memory usage but makes building the table slower.
+const KEYS_PER_BUCKET: usize = 4;
+
+/// The multiplier used by `SeededHasher`, the fractional part of pi.
+const MULTIPLIER: u64 = 0x243f_6a88_85a3_08d3;
+
+/// The value added to the seed in case no perfect hash function was found with the current one.
+const SEED_INCREMENT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// The number of displacements tried per key before a different seed is used.
+const MAX_TRIES_PER_KEY: usize = 32;
+
+/// The number of seeds tried before keys with colliding hashes are moved out of the perfect hash table.
+const MAX_SEEDS: usize = 16;
+
+/// A `key`-`element` pair stored together with the hash of its key.
+type Entry<K, T> = (u64, K, T);
+
+/// An immutable map created by [`Holder::freeze`](struct.Holder.html#method.freeze), which can be shared between threads.
+///
+/// The elements are stored in a single table without any empty slots. The position of each key is computed using
+/// a minimal perfect hash function, so every lookup hashes the key once using a fast hasher and compares it with exactly
+/// one stored entry. The stored hash is compared first, so most missing keys are rejected without reading the stored key.
+/// This is usually faster than a lookup in a `HashMap`, at the cost of slow construction.
+///
+/// Keys whose hashes always collide, for example due to a poor `Hash` implementation, can not be placed in the table.
+/// These are searched linearly instead, so building the table always terminates.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let holder = holder::Holder::new();
+/// holder.insert("player", 42);
+/// holder.insert("enemy", 7);
+///
+/// let frozen = Arc::new(holder.freeze());
+/// let render = {
+///     let frozen = frozen.clone();
+///     thread::spawn(move || *frozen.get("player").unwrap())
+/// };
+///
+/// assert_eq!(frozen.get("enemy"), Some(&7));
+/// assert_eq!(render.join().unwrap(), 42);
+/// ```
+pub struct FrozenHolder<T, K = String> {
+    seed: u64,
+    /// The displacements of each bucket, used to compute the position of its keys.
+    displacements: Vec<u32>,
+    /// The number of entries placed by the perfect hash function.
+    table_len: usize,
+    /// The entries of the perfect hash table, followed by the entries which could not be placed in it.
+    entries: Vec<Entry<K, T>>,
+}
+
+impl<T, K: Eq + Hash> FrozenHolder<T, K> {
+    /// Returns a reference to the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    ///
+    /// let frozen = holder.freeze();
+    /// assert_eq!(frozen.get("a"), Some(&42));
+    /// assert_eq!(frozen.get("b"), None);
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let hashes = Hashes::new(self.seed, key);
+        let (table, overflow) = self.entries.split_at(self.table_len);
+        if !table.is_empty() {
+            let displacement = self.displacements[hashes.bucket(self.displacements.len())];
+            let (hash, ref existing, ref element) = table[hashes.index(displacement, table.len())];
+            if hash == hashes.position && existing.borrow() == key {
+                return Some(element);
+            }
+        }
+
+        overflow.iter()
+            .find(|&&(hash, ref existing, _)| hash == hashes.position && existing.borrow() == key)
+            .map(|(_, _, element)| element)
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    /// holder.insert("b", 360);
+    /// assert_eq!(holder.freeze().len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::Holder<u32> = holder::Holder::new();
+    /// assert!(holder.freeze().is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// An iterator visiting all `key`-`element` pairs in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    ///
+    /// let frozen = holder.freeze();
+    /// assert_eq!(frozen.iter().map(|(_, element)| element).sum::<u32>(), 3);
+    /// ```
+    pub fn iter(&self) -> FrozenIter<'_, T, K> {
+        FrozenIter {
+            inner: self.entries.iter(),
+        }
+    }
+
+    /// Builds the perfect hash table, `entries` must not contain duplicate keys.
+    fn build(mut entries: Vec<(K, T)>) -> Self {
+        let mut overflow = Vec::new();
+        let mut seed = 0;
+        loop {
+            let buckets = entries.len().div_ceil(KEYS_PER_BUCKET).max(1);
+            let mut hashes = Vec::new();
+            for _ in 0..MAX_SEEDS {
+                hashes = entries.iter().map(|(key, _)| Hashes::new(seed, key)).collect();
+                if let Some((displacements, positions)) = Self::displace(buckets, &hashes) {
+                    let mut table: Vec<Option<Entry<K, T>>> = entries.iter().map(|_| None).collect();
+                    for (((key, element), hashes), position) in entries.into_iter().zip(hashes).zip(positions) {
+                        table[position] = Some((hashes.position, key, element));
+                    }
+
+                    let table_len = table.len();
+                    let overflow = overflow.into_iter()
+                        .map(|(key, element)| (Hashes::new(seed, &key).position, key, element));
+                    return FrozenHolder {
+                        seed,
+                        displacements,
+                        table_len,
+                        entries: table.into_iter().map(|entry| entry.unwrap()).chain(overflow).collect(),
+                    };
+                }
+                seed = seed.wrapping_add(SEED_INCREMENT);
+            }
+
+            // keys with equal hashes can never be separated, so they are moved out of the table.
+            let mut counts = HashMap::new();
+            for hashes in &hashes {
+                *counts.entry(hashes.position).or_insert(0) += 1;
+            }
+            let colliding = hashes.iter().map(|hashes| counts[&hashes.position] > 1);
+            let (colliding, rest): (Vec<_>, Vec<_>) = entries.into_iter().zip(colliding)
+                .partition(|&(_, colliding)| colliding);
+
+            // in the unlikely case that there is no perfect hash function without any colliding hashes,
+            // all keys are moved out of the table to guarantee that building terminates.
+            if colliding.is_empty() {
+                overflow.extend(rest.into_iter().map(|(entry, _)| entry));
+                entries = Vec::new();
+            }
+            else {
+                overflow.extend(colliding.into_iter().map(|(entry, _)| entry));
+                entries = rest.into_iter().map(|(entry, _)| entry).collect();
+            }
+        }
+    }
+
+    /// Tries to find a displacement for each bucket, so that every key is moved to a different position.
+    ///
+    /// Returns the displacements and the position of each entry,
+    /// or `None` in case there is no perfect hash function for these `hashes`.
+    fn displace(buckets: usize, hashes: &[Hashes]) -> Option<(Vec<u32>, Vec<usize>)> {
+        let len = hashes.len();
+
+        let mut bucket_entries = vec![Vec::new(); buckets];
+        for (i, hashes) in hashes.iter().enumerate() {
+            bucket_entries[hashes.bucket(buckets)].push(i);
+        }
+
+        // buckets with many keys are the hardest to place, so they are placed first.
+        let mut order: Vec<usize> = (0..buckets).collect();
+        order.sort_unstable_by_key(|&bucket| Reverse(bucket_entries[bucket].len()));
+
+        let max_tries = (len * MAX_TRIES_PER_KEY).min(u32::MAX as usize) as u32;
+        let mut displacements = vec![0; buckets];
+        let mut positions = vec![0; len];
+        let mut occupied = vec![false; len];
+        // `tried[position] == generation` in case the current attempt already uses this position.
+        let mut tried = vec![0u64; len];
+        let mut generation = 0;
+
+        for bucket in order {
+            let bucket_entries = &bucket_entries[bucket];
+            if bucket_entries.is_empty() {
+                continue;
+            }
+
+            let found = (0..max_tries).find(|&displacement| {
+                generation += 1;
+                bucket_entries.iter().all(|&i| {
+                    let position = hashes[i].index(displacement, len);
+                    if occupied[position] || tried[position] == generation {
+                        return false;
+                    }
+                    tried[position] = generation;
+                    true
+                })
+            })?;
+
+            displacements[bucket] = found;
+            for &i in bucket_entries {
+                let position = hashes[i].index(found, len);
+                occupied[position] = true;
+                positions[i] = position;
+            }
+        }
+
+        Some((displacements, positions))
+    }
+}
+
+impl<T, K: Eq + Hash, S: BuildHasher> Holder<T, K, S> {
+    /// Converts the `Holder<T>` into an immutable [`FrozenHolder<T>`](struct.FrozenHolder.html), which has faster
+    /// lookups and can be shared between threads.
+    ///
+    /// Building the perfect hash table takes a lot longer than inserting the elements into a `Holder<T>`,
+    /// so this should only be used once all elements are loaded. Ids of this `Holder<T>` can not be used with the result.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// for i in 0..1000 {
+    ///     holder.insert(&format!("sprite_{}", i), i);
+    /// }
+    ///
+    /// let frozen = holder.freeze();
+    /// assert_eq!(frozen.len(), 1000);
+    /// assert_eq!(frozen.get("sprite_42"), Some(&42));
+    /// assert_eq!(frozen.get("sprite_1000"), None);
+    /// ```
+    ///
+    /// Keys whose hashes always collide are still found, although their lookups are slower:
+    ///
+    /// ```
+    /// use crow_util::holder;
+    /// use std::hash::{Hash, Hasher};
+    ///
+    /// #[derive(Clone, PartialEq, Eq)]
+    /// struct Name(&'static str);
+    ///
+    /// impl Hash for Name {
+    ///     fn hash<H: Hasher>(&self, _: &mut H) {}
+    /// }
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert(&Name("player"), 42);
+    /// holder.insert(&Name("enemy"), 7);
+    ///
+    /// let frozen = holder.freeze();
+    /// assert_eq!(frozen.get(&Name("player")), Some(&42));
+    /// assert_eq!(frozen.get(&Name("enemy")), Some(&7));
+    /// assert_eq!(frozen.get(&Name("boss")), None);
+    /// assert_eq!(frozen.iter().count(), 2);
+    /// ```
+    pub fn freeze(self) -> FrozenHolder<T, K> {
+        FrozenHolder::build(self.into_iter().collect())
+    }
+}
+
+impl<T: fmt::Debug, K: fmt::Debug + Eq + Hash> fmt::Debug for FrozenHolder<T, K> {
+    /// Formats the `FrozenHolder<T>` as a map.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T, K, Q: ?Sized> Index<&Q> for FrozenHolder<T, K>
+where K: Eq + Hash + Borrow<Q>, Q: Eq + Hash + fmt::Debug {
+    type Output = T;
+
+    /// Returns a reference to the element corresponding to the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics in case the `key` is not present in the `FrozenHolder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder.freeze()["a"], 42);
+    /// ```
+    fn index(&self, key: &Q) -> &T {
+        match self.get(key) {
+            Some(element) => element,
+            None => panic!("no element found for key {:?}", key),
+        }
+    }
+}
+
+impl<'a, T, K: Eq + Hash> IntoIterator for &'a FrozenHolder<T, K> {
+    type Item = (&'a K, &'a T);
+    type IntoIter = FrozenIter<'a, T, K>;
+
+    fn into_iter(self) -> FrozenIter<'a, T, K> {
+        self.iter()
+    }
+}
+
+/// An iterator over the `key`-`element` pairs of a [`FrozenHolder<T>`](struct.FrozenHolder.html).
+///
+/// This struct is created by [`FrozenHolder::iter`](struct.FrozenHolder.html#method.iter).
+pub struct FrozenIter<'a, T: 'a, K: 'a = String> {
+    inner: slice::Iter<'a, Entry<K, T>>,
+}
+
+impl<'a, T, K> Iterator for FrozenIter<'a, T, K> {
+    type Item = (&'a K, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, key, element)| (key, element))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, K> ExactSizeIterator for FrozenIter<'a, T, K> {}
+
+/// The hashes of a single key, used to compute its bucket and its position.
+struct Hashes {
+    bucket: u32,
+    position: u64,
+}
+
+impl Hashes {
+    fn new<Q: ?Sized + Hash>(seed: u64, key: &Q) -> Self {
+        let mut hasher = SeededHasher {
+            hash: seed,
+        };
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        Hashes {
+            bucket: (hash >> 32) as u32,
+            position: hash,
+        }
+    }
+
+    fn bucket(&self, buckets: usize) -> usize {
+        reduce(self.bucket, buckets)
+    }
+
+    fn index(&self, displacement: u32, len: usize) -> usize {
+        let hash = (self.position ^ u64::from(displacement)).wrapping_mul(MULTIPLIER);
+        reduce((hash >> 32) as u32, len)
+    }
+}
+
+/// Maps `hash` to `0..len`, which is a lot faster than `hash % len`.
+fn reduce(hash: u32, len: usize) -> usize {
+    ((u64::from(hash) * len as u64) >> 32) as usize
+}
+
+/// A fast hasher which, unlike `FxHasher`, does not produce equal hashes for similar keys of different lengths.
+///
+/// Each word is combined with the current state using a folded multiplication, just like `aHash` does without AES.
+struct SeededHasher {
+    hash: u64,
+}
+
+impl SeededHasher {
+    fn add_to_hash(&mut self, word: u64) {
+        let full = u128::from(self.hash ^ word) * u128::from(MULTIPLIER);
+        self.hash = full as u64 ^ (full >> 64) as u64;
+    }
+}
+
+impl Hasher for SeededHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        // the length is hashed as well, as the last word is padded differently depending on the length.
+        self.add_to_hash(bytes.len() as u64);
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.add_to_hash(u64::from_le_bytes([word[0], word[1], word[2], word[3], word[4], word[5], word[6], word[7]]));
+        }
+
+        // reading the remaining bytes without copying them into a buffer is a lot faster.
+        let rest = words.remainder();
+        let word = match rest.len() {
+            0 => return,
+            len @ 1..=3 => u64::from(rest[0]) << 16 | u64::from(rest[len / 2]) << 8 | u64::from(rest[len - 1]),
+            len => {
+                let first = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+                let last = u32::from_le_bytes([rest[len - 4], rest[len - 3], rest[len - 2], rest[len - 1]]);
+                u64::from(first) << 32 | u64::from(last)
+            }
+        };
+        self.add_to_hash(word);
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add_to_hash(u64::from(i));
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add_to_hash(i);
+    }
+
+    fn finish(&self) -> u64 {
+        // mix the upper bits into the lower ones, using the finalizer of `MurmurHash3`.
+        let mut hash = self.hash;
+        hash = (hash ^ (hash >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash = (hash ^ (hash >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^ (hash >> 33)
+    }
+}
@@ -0,0 +1,42 @@
+//! Holders using faster hashers than the default `RandomState`, only available with the `fxhash` or `ahash` feature.
+#[cfg(feature = "ahash")]
+use ahash;
+
+use super::Holder;
+
+#[cfg(feature = "fxhash")]
+pub use rustc_hash::{FxBuildHasher, FxHasher};
+
+/// A `Holder<T>` using the [`FxHasher`](struct.FxHasher.html) of the Rust compiler instead of the default `RandomState`.
+///
+/// `FxHasher` is a lot faster than the default `SipHash` for short keys, but it is not resistant against HashDoS attacks,
+/// so it should only be used for trusted keys like asset names. Requires the `fxhash` feature.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let holder: holder::FxHolder<u32> = holder::FxHolder::default();
+/// holder.insert("player", 42);
+/// assert_eq!(holder.get("player"), Some(&42));
+/// ```
+#[cfg(feature = "fxhash")]
+pub type FxHolder<T, K = String> = Holder<T, K, FxBuildHasher>;
+
+/// A `Holder<T>` using [aHash](https://docs.rs/ahash) instead of the default `RandomState`.
+///
+/// Just like `RandomState`, aHash is randomly seeded, which makes HashDoS attacks a lot harder,
+/// while still being almost as fast as [`FxHolder<T>`](type.FxHolder.html). Requires the `ahash` feature.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let holder: holder::AHolder<u32> = holder::AHolder::default();
+/// holder.insert("player", 42);
+/// assert_eq!(holder.get("player"), Some(&42));
+/// ```
+#[cfg(feature = "ahash")]
+pub type AHolder<T, K = String> = Holder<T, K, ahash::RandomState>;
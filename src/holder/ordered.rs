@@ -0,0 +1,283 @@
+//! A version of `Holder<T>` which remembers the order in which its elements were inserted.
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::StableBox;
+
+/// A map which keeps its elements in insertion order, allowing them to be accessed by key or by index.
+///
+/// The iteration order of [`Holder<T>`](struct.Holder.html) depends on its randomly seeded hasher, so it changes between runs.
+/// `OrderedHolder<T>` always iterates in insertion order, which is required for replays or lockstep networking,
+/// and assigns each element an index, which can be used with [`get_index`](#method.get_index).
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let holder = holder::OrderedHolder::new();
+/// holder.insert("zombie", 3);
+/// holder.insert("archer", 1);
+/// holder.insert("knight", 2);
+///
+/// assert_eq!(holder.keys().collect::<Vec<_>>(), ["zombie", "archer", "knight"]);
+/// assert_eq!(holder.get_index(1), Some(("archer", &1)));
+/// assert_eq!(holder.get("knight"), Some(&2));
+/// ```
+pub struct OrderedHolder<T> {
+    indices: UnsafeCell<HashMap<String, usize>>,
+    entries: UnsafeCell<Vec<(StableBox<str>, StableBox<T>)>>,
+}
+
+impl<T> OrderedHolder<T> {
+    /// Constructs a new, empty `OrderedHolder<T>`.
+    pub fn new() -> Self {
+        OrderedHolder {
+            indices: UnsafeCell::new(HashMap::new()),
+            entries: UnsafeCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to the key.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.index_of(key).and_then(|index| self.get_index(index)).map(|(_, element)| element)
+    }
+
+    /// Returns the key and element at `index`, which is the number of elements inserted before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::OrderedHolder::new();
+    /// holder.insert("b", 2);
+    /// holder.insert("a", 1);
+    ///
+    /// assert_eq!(holder.get_index(0), Some(("b", &2)));
+    /// assert_eq!(holder.get_index(1), Some(("a", &1)));
+    /// assert_eq!(holder.get_index(2), None);
+    /// ```
+    pub fn get_index(&self, index: usize) -> Option<(&str, &T)> {
+        let entries = unsafe {& *self.entries.get() };
+        entries.get(index).map(|(key, element)| (key.get(), element.get()))
+    }
+
+    /// Returns the index of the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::OrderedHolder::new();
+    /// holder.insert("b", 2);
+    /// holder.insert("a", 1);
+    ///
+    /// assert_eq!(holder.index_of("a"), Some(1));
+    /// assert_eq!(holder.index_of("c"), None);
+    /// ```
+    pub fn index_of(&self, key: &str) -> Option<usize> {
+        let indices = unsafe {& *self.indices.get() };
+        indices.get(key).cloned()
+    }
+
+    /// Inserts an `element` accessible by `key` after all existing elements.
+    ///
+    /// In case the `key` was already present, the old `element` is returned, the new one is ignored and the order is not changed.
+    /// This method can be used while `OrderedHolder<T>` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::OrderedHolder::new();
+    /// holder.insert("a", 1);
+    /// holder.insert("b", 2);
+    /// assert_eq!(holder.insert("a", 3), Some(&1));
+    /// assert_eq!(holder.keys().collect::<Vec<_>>(), ["a", "b"]);
+    /// ```
+    pub fn insert(&self, key: &str, element: T) -> Option<&T> {
+        match self.insert_full(key, element) {
+            (existing, false) => Some(existing),
+            (_, true) => None,
+        }
+    }
+
+    /// Inserts an `element`, which is created by a closure and can be accessed by `key`, after all existing elements.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the closure is not called.
+    /// Just like with [`Holder::insert_fn`](struct.Holder.html#method.insert_fn), the closure itself may use this `OrderedHolder<T>`,
+    /// in which case the elements inserted by the closure are ordered before `element`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::OrderedHolder::new();
+    /// assert_eq!(holder.insert_fn("a", || 42), None);
+    /// assert_eq!(holder.insert_fn("a", || unreachable!()), Some(&42));
+    ///
+    /// holder.insert_fn("level", || *holder.get_or_insert_with("tileset", || 7));
+    /// assert_eq!(holder.keys().collect::<Vec<_>>(), ["a", "tileset", "level"]);
+    /// ```
+    pub fn insert_fn<F>(&self, key: &str, element: F) -> Option<&T>
+    where F: FnOnce() -> T {
+        match self.get(key) {
+            Some(existing) => Some(existing),
+            None => self.insert(key, element()),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an element created by
+    /// the closure after all existing elements in case the `key` was not present.
+    pub fn get_or_insert_with<F>(&self, key: &str, element: F) -> &T
+    where F: FnOnce() -> T {
+        match self.get(key) {
+            Some(existing) => existing,
+            None => self.insert_full(key, element()).0,
+        }
+    }
+
+    /// An iterator visiting all `key`-`element` pairs in insertion order.
+    ///
+    /// Elements inserted while iterating are not visited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::OrderedHolder::new();
+    /// holder.insert("b", 2);
+    /// holder.insert("a", 1);
+    ///
+    /// for (key, &element) in holder.iter() {
+    ///     holder.insert(&format!("{}{}", key, key), element * 10);
+    /// }
+    /// assert_eq!(holder.iter().collect::<Vec<_>>(), [("b", &2), ("a", &1), ("bb", &20), ("aa", &10)]);
+    /// ```
+    pub fn iter(&self) -> OrderedIter<'_, T> {
+        OrderedIter {
+            holder: self,
+            front: 0,
+            back: self.len(),
+        }
+    }
+
+    /// An iterator visiting all keys in insertion order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::OrderedHolder::new();
+    /// holder.insert("b", 2);
+    /// holder.insert("a", 1);
+    /// assert_eq!(holder.keys().collect::<Vec<_>>(), ["b", "a"]);
+    /// ```
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Clears the map, removing all `key`-`element` pairs.
+    pub fn clear(&mut self) {
+        self.indices.get_mut().clear();
+        self.entries.get_mut().clear();
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        unsafe { & *self.entries.get() }.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        unsafe { & *self.entries.get() }.is_empty()
+    }
+
+    /// Inserts `element` in case `key` is not already present.
+    ///
+    /// Returns a reference to the element corresponding to `key` and whether `element` was inserted.
+    fn insert_full(&self, key: &str, element: T) -> (&T, bool) {
+        if let Some(existing) = self.get(key) {
+            return (existing, false);
+        }
+
+        let element = StableBox::new(element);
+        let ptr = element.ptr;
+        let indices = unsafe {&mut *self.indices.get() };
+        let entries = unsafe {&mut *self.entries.get() };
+        indices.insert(key.to_owned(), entries.len());
+        entries.push((StableBox::from_box(key.into()), element));
+        (unsafe { &*ptr.as_ptr() }, true)
+    }
+}
+
+impl<T> Default for OrderedHolder<T> {
+    /// Creates an empty `OrderedHolder<T>`.
+    fn default() -> Self {
+        OrderedHolder::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OrderedHolder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a OrderedHolder<T> {
+    type Item = (&'a str, &'a T);
+    type IntoIter = OrderedIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over `key`-`element` pairs of an [`OrderedHolder<T>`](struct.OrderedHolder.html) in insertion order.
+///
+/// This struct is created by [`OrderedHolder::iter`](struct.OrderedHolder.html#method.iter).
+pub struct OrderedIter<'a, T: 'a> {
+    holder: &'a OrderedHolder<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Iterator for OrderedIter<'a, T> {
+    type Item = (&'a str, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.front += 1;
+            self.holder.get_index(self.front - 1)
+        }
+        else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for OrderedIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front < self.back {
+            self.back -= 1;
+            self.holder.get_index(self.back)
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl<'a, T> ExactSizeIterator for OrderedIter<'a, T> {}
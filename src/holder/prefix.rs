@@ -0,0 +1,492 @@
+//! A version of `Holder<T>` with hierarchical string keys, which supports prefix queries.
+use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::ops::Bound;
+use std::vec;
+
+use super::StableBox;
+
+/// An ordered map of `String` keys, which can efficiently find all elements whose key starts with a given prefix.
+///
+/// Keys are usually hierarchical paths like `textures/player/idle_03`. Prefix queries only visit the matching keys
+/// instead of scanning the entire map, at the cost of `O(log n)` lookups instead of the constant time lookups of
+/// [`Holder<T>`](struct.Holder.html).
+///
+/// Unlike `Holder<T>`, the keys are always `String`s and there is no hasher parameter, as the keys are compared
+/// by their order instead of being hashed.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let holder = holder::PrefixHolder::new();
+/// holder.insert("textures/player/idle_00", 0);
+/// holder.insert("textures/player/idle_01", 1);
+/// holder.insert("textures/enemy/idle_00", 2);
+/// holder.insert("sounds/step", 3);
+///
+/// assert_eq!(holder.count_prefix("textures/"), 3);
+///
+/// let player = holder.subtree("textures/player/");
+/// assert_eq!(player.get("idle_01"), Some(&1));
+/// assert_eq!(player.keys().collect::<Vec<_>>(), ["idle_00", "idle_01"]);
+/// ```
+pub struct PrefixHolder<T> {
+    items: UnsafeCell<BTreeMap<String, StableBox<T>>>,
+}
+
+impl<T> PrefixHolder<T> {
+    /// Constructs a new, empty `PrefixHolder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::PrefixHolder<u32> = holder::PrefixHolder::new();
+    /// assert_eq!(holder.len(), 0);
+    /// ```
+    pub fn new() -> Self {
+        PrefixHolder {
+            items: UnsafeCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player", 42);
+    /// assert_eq!(holder.get("textures/player"), Some(&42));
+    /// assert_eq!(holder.get("textures/"), None);
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&T> {
+        let items = unsafe {& *self.items.get() };
+        items.get(key).map(|element| element.get())
+    }
+
+    /// Inserts an `element` accessible by `key`.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the new one is ignored.
+    /// This method can be used while `PrefixHolder<T>` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// assert_eq!(holder.insert("a", 42), None);
+    /// assert_eq!(holder.insert("a", 25), Some(&42));
+    /// ```
+    pub fn insert(&self, key: &str, element: T) -> Option<&T> {
+        match self.insert_full(key, element) {
+            (existing, false) => Some(existing),
+            (_, true) => None,
+        }
+    }
+
+    /// Inserts an `element`, which is created by a closure and can be accessed by `key`.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the closure is not called.
+    /// Just like with [`Holder::insert_fn`](struct.Holder.html#method.insert_fn), the closure itself may use this `PrefixHolder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// assert_eq!(holder.insert_fn("a", || 42), None);
+    /// assert_eq!(holder.insert_fn("a", || unreachable!()), Some(&42));
+    /// ```
+    pub fn insert_fn<F>(&self, key: &str, element: F) -> Option<&T>
+    where F: FnOnce() -> T {
+        match self.get(key) {
+            Some(existing) => Some(existing),
+            None => self.insert(key, element()),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an element created by
+    /// the closure in case the `key` was not present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// assert_eq!(holder.get_or_insert_with("a", || 42), &42);
+    /// assert_eq!(holder.get_or_insert_with("a", || 25), &42);
+    /// ```
+    pub fn get_or_insert_with<F>(&self, key: &str, element: F) -> &T
+    where F: FnOnce() -> T {
+        match self.get(key) {
+            Some(existing) => existing,
+            None => self.insert_full(key, element()).0,
+        }
+    }
+
+    /// An iterator visiting all `key`-`element` pairs whose key starts with `prefix`, ordered by their keys.
+    ///
+    /// Only the matching keys are visited. Just like [`Holder::iter`](struct.Holder.html#method.iter),
+    /// this iterator works on a snapshot and allows insertions while iterating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player/idle_01", 1);
+    /// holder.insert("textures/player/idle_00", 0);
+    /// holder.insert("textures/playground", 2);
+    ///
+    /// let player: Vec<_> = holder.iter_prefix("textures/player/").collect();
+    /// assert_eq!(player, [("textures/player/idle_00", &0), ("textures/player/idle_01", &1)]);
+    /// ```
+    pub fn iter_prefix(&self, prefix: &str) -> PrefixIter<'_, T> {
+        PrefixIter {
+            inner: self.range(prefix)
+                .take_while(|&(key, _)| key.starts_with(prefix))
+                .map(|(key, element)| (key.as_str(), element.get()))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        }
+    }
+
+    /// Returns the number of elements whose key starts with `prefix`.
+    ///
+    /// No counts are stored per prefix, so this visits all matching keys and takes `O(log n + m)` time,
+    /// where `m` is the number of matching keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player", 0);
+    /// holder.insert("textures/enemy", 1);
+    /// holder.insert("sounds/step", 2);
+    ///
+    /// assert_eq!(holder.count_prefix("textures/"), 2);
+    /// assert_eq!(holder.count_prefix(""), 3);
+    /// assert_eq!(holder.count_prefix("models/"), 0);
+    /// ```
+    pub fn count_prefix(&self, prefix: &str) -> usize {
+        self.range(prefix).take_while(|&(key, _)| key.starts_with(prefix)).count()
+    }
+
+    /// Returns a view of all elements whose key starts with `prefix`, with the prefix stripped from their keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// let textures = holder.subtree("textures/");
+    /// textures.insert("player", 42);
+    ///
+    /// assert_eq!(holder.get("textures/player"), Some(&42));
+    /// assert_eq!(textures.get("player"), Some(&42));
+    /// assert_eq!(textures.len(), 1);
+    /// ```
+    pub fn subtree(&self, prefix: &str) -> Subtree<'_, T> {
+        Subtree {
+            holder: self,
+            prefix: prefix.to_owned(),
+        }
+    }
+
+    /// An iterator visiting all `key`-`element` pairs, ordered by their keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("b", 2);
+    /// holder.insert("a", 1);
+    ///
+    /// assert_eq!(holder.iter().collect::<Vec<_>>(), [("a", &1), ("b", &2)]);
+    /// ```
+    pub fn iter(&self) -> PrefixIter<'_, T> {
+        self.iter_prefix("")
+    }
+
+    /// Clears the map, removing all `key`-`element` pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::PrefixHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.clear();
+    /// assert!(holder.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.items.get_mut().clear();
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.insert("b", 360);
+    /// assert_eq!(holder.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        unsafe { & *self.items.get() }.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// assert!(holder.is_empty());
+    /// holder.insert("a", 42);
+    /// assert!(!holder.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        unsafe { & *self.items.get() }.is_empty()
+    }
+
+    /// Inserts `element` in case `key` is not already present.
+    ///
+    /// Returns a reference to the element corresponding to `key` and whether `element` was inserted.
+    fn insert_full(&self, key: &str, element: T) -> (&T, bool) {
+        if let Some(existing) = self.get(key) {
+            return (existing, false);
+        }
+
+        let element = StableBox::new(element);
+        let ptr = element.ptr;
+        let items = unsafe {&mut *self.items.get() };
+        items.insert(key.to_owned(), element);
+        (unsafe { &*ptr.as_ptr() }, true)
+    }
+
+    /// Returns all entries starting at `prefix`.
+    ///
+    /// All keys starting with `prefix` are ordered directly after `prefix` itself,
+    /// so taking entries while their key starts with `prefix` only visits the matching keys.
+    fn range(&self, prefix: &str) -> btree_map::Range<'_, String, StableBox<T>> {
+        let items = unsafe {& *self.items.get() };
+        items.range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+    }
+}
+
+impl<T> Default for PrefixHolder<T> {
+    /// Creates an empty `PrefixHolder<T>`.
+    fn default() -> Self {
+        PrefixHolder::new()
+    }
+}
+
+/// A view of all elements of a [`PrefixHolder<T>`](struct.PrefixHolder.html) whose key starts with a given prefix.
+///
+/// All keys used by this view are relative to its prefix. This struct is created by
+/// [`PrefixHolder::subtree`](struct.PrefixHolder.html#method.subtree).
+pub struct Subtree<'a, T: 'a> {
+    holder: &'a PrefixHolder<T>,
+    prefix: String,
+}
+
+impl<'a, T> Subtree<'a, T> {
+    /// Returns the prefix of this view.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::PrefixHolder<u32> = holder::PrefixHolder::new();
+    /// assert_eq!(holder.subtree("textures/").prefix(), "textures/");
+    /// ```
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns a reference to the element corresponding to `prefix` followed by `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player", 42);
+    /// assert_eq!(holder.subtree("textures/").get("player"), Some(&42));
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&'a T> {
+        self.holder.get(&self.full_key(key))
+    }
+
+    /// Inserts an `element` accessible by `prefix` followed by `key`.
+    ///
+    /// In case the key was already present, the old `element` is returned and the new one is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// let sounds = holder.subtree("sounds/");
+    /// assert_eq!(sounds.insert("step", 42), None);
+    /// assert_eq!(holder.insert("sounds/step", 25), Some(&42));
+    /// ```
+    pub fn insert(&self, key: &str, element: T) -> Option<&'a T> {
+        self.holder.insert(&self.full_key(key), element)
+    }
+
+    /// An iterator visiting all `key`-`element` pairs of this view ordered by their keys, with the prefix stripped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player", 0);
+    /// holder.insert("textures/enemy", 1);
+    /// holder.insert("sounds/step", 2);
+    ///
+    /// let textures: Vec<_> = holder.subtree("textures/").iter().collect();
+    /// assert_eq!(textures, [("enemy", &1), ("player", &0)]);
+    /// ```
+    pub fn iter(&self) -> PrefixIter<'a, T> {
+        let prefix = &self.prefix;
+        PrefixIter {
+            inner: self.holder.range(prefix)
+                .take_while(|&(key, _)| key.starts_with(prefix))
+                .map(|(key, element)| (&key[prefix.len()..], element.get()))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        }
+    }
+
+    /// An iterator visiting all keys of this view in order, with the prefix stripped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player", 0);
+    /// holder.insert("textures/enemy", 1);
+    ///
+    /// assert_eq!(holder.subtree("textures/").keys().collect::<Vec<_>>(), ["enemy", "player"]);
+    /// ```
+    pub fn keys(&self) -> impl Iterator<Item = &'a str> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Returns a view of all elements of this view whose key starts with `prefix`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player/idle", 42);
+    ///
+    /// let player = holder.subtree("textures/").subtree("player/");
+    /// assert_eq!(player.prefix(), "textures/player/");
+    /// assert_eq!(player.get("idle"), Some(&42));
+    /// ```
+    pub fn subtree(&self, prefix: &str) -> Subtree<'a, T> {
+        self.holder.subtree(&self.full_key(prefix))
+    }
+
+    /// Returns the number of elements in this view.
+    ///
+    /// Just like [`PrefixHolder::count_prefix`](struct.PrefixHolder.html#method.count_prefix), this visits all elements of this view.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player", 0);
+    /// holder.insert("sounds/step", 1);
+    /// assert_eq!(holder.subtree("textures/").len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.holder.count_prefix(&self.prefix)
+    }
+
+    /// Returns `true` if this view contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::PrefixHolder::new();
+    /// holder.insert("textures/player", 0);
+    /// assert!(holder.subtree("models/").is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.holder.range(&self.prefix).next().is_none_or(|(key, _)| !key.starts_with(&self.prefix))
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        let mut full = String::with_capacity(self.prefix.len() + key.len());
+        full.push_str(&self.prefix);
+        full.push_str(key);
+        full
+    }
+}
+
+/// An iterator over `key`-`element` pairs of a [`PrefixHolder<T>`](struct.PrefixHolder.html), ordered by their keys.
+///
+/// This struct is created by [`PrefixHolder::iter_prefix`](struct.PrefixHolder.html#method.iter_prefix)
+/// and [`Subtree::iter`](struct.Subtree.html#method.iter).
+pub struct PrefixIter<'a, T: 'a> {
+    inner: vec::IntoIter<(&'a str, &'a T)>,
+}
+
+impl<'a, T> Iterator for PrefixIter<'a, T> {
+    type Item = (&'a str, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for PrefixIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, T> ExactSizeIterator for PrefixIter<'a, T> {}
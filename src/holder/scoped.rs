@@ -0,0 +1,265 @@
+//! A layer on top of a `Holder<T>`, which is used for elements with a shorter lifetime.
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+use super::Holder;
+
+/// A `Holder<T>` which falls back to a parent `Holder<T>` in case a key is not present.
+///
+/// New elements are only inserted into the local layer, which can also override elements of the parent.
+/// This is useful for scenes, which have their own assets but should still be able to use the global ones.
+/// Dropping the `ScopedHolder<T>` drops all of its local elements at once, while the parent is not affected.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let global = holder::Holder::new();
+/// global.insert("font", "global font");
+/// global.insert("tileset", "global tileset");
+///
+/// {
+///     let level = holder::ScopedHolder::new(&global);
+///     level.insert("tileset", "level tileset");
+///     level.insert("boss", "level boss");
+///
+///     assert_eq!(level.get("font"), Some(&"global font"));
+///     assert_eq!(level.get("tileset"), Some(&"level tileset"));
+///     assert_eq!(global.get("boss"), None);
+/// }
+///
+/// assert_eq!(global.get("tileset"), Some(&"global tileset"));
+/// ```
+pub struct ScopedHolder<'p, T: ?Sized + 'p, K: 'p = String, S: 'p = RandomState> {
+    parent: &'p Holder<T, K, S>,
+    local: Holder<T, K, S>,
+}
+
+impl<'p, T: ?Sized, K: Eq + Hash, S: BuildHasher + Clone> ScopedHolder<'p, T, K, S> {
+    /// Constructs a new, empty `ScopedHolder<T>` on top of `parent`, using the same hasher as the parent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global: holder::Holder<u32> = holder::Holder::new();
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// assert!(scope.is_empty());
+    /// ```
+    pub fn new(parent: &'p Holder<T, K, S>) -> Self {
+        ScopedHolder {
+            parent,
+            local: Holder::with_hasher(parent.hasher().clone()),
+        }
+    }
+}
+
+impl<'p, T: ?Sized, K: Eq + Hash, S: BuildHasher> ScopedHolder<'p, T, K, S> {
+    /// Returns a reference to the parent `Holder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// scope.parent().insert("a", 42);
+    /// assert_eq!(global.get("a"), Some(&42));
+    /// ```
+    pub fn parent(&self) -> &'p Holder<T, K, S> {
+        self.parent
+    }
+
+    /// Returns a reference to the local layer, which only contains the elements inserted into this `ScopedHolder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// global.insert("a", 1);
+    ///
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// scope.insert("b", 2);
+    /// assert_eq!(scope.local().get("a"), None);
+    /// assert_eq!(scope.local().get("b"), Some(&2));
+    /// ```
+    pub fn local(&self) -> &Holder<T, K, S> {
+        &self.local
+    }
+
+    /// Returns a reference to the element corresponding to the key, checking the local layer first
+    /// and falling back to the parent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// global.insert("a", 1);
+    /// global.insert("b", 2);
+    ///
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// scope.insert("b", 20);
+    ///
+    /// assert_eq!(scope.get("a"), Some(&1));
+    /// assert_eq!(scope.get("b"), Some(&20));
+    /// assert_eq!(scope.get("c"), None);
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.local.get(key).or_else(|| self.parent.get(key))
+    }
+
+    /// Inserts an already boxed `element` accessible by `key` into the local layer.
+    ///
+    /// This is the equivalent of [`insert`](#method.insert) which also works for unsized types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global: holder::Holder<str> = holder::Holder::new();
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// assert_eq!(scope.insert_boxed("greeting", "hello".into()), None);
+    /// assert_eq!(scope.get("greeting"), Some("hello"));
+    /// ```
+    pub fn insert_boxed<Q>(&self, key: &Q, element: Box<T>) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.local.insert_boxed(key, element)
+    }
+
+    /// Clears the local layer, dropping all local elements. The parent is not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// global.insert("a", 1);
+    ///
+    /// let mut scope = holder::ScopedHolder::new(&global);
+    /// scope.insert("a", 10);
+    /// scope.clear();
+    /// assert_eq!(scope.get("a"), Some(&1));
+    /// ```
+    pub fn clear(&mut self) {
+        self.local.clear();
+    }
+
+    /// Returns the number of elements in the local layer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// global.insert("a", 1);
+    ///
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// scope.insert("b", 2);
+    /// assert_eq!(scope.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.local.len()
+    }
+
+    /// Returns `true` if the local layer contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// global.insert("a", 1);
+    ///
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// assert!(scope.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.local.is_empty()
+    }
+}
+
+impl<'p, T, K: Eq + Hash, S: BuildHasher> ScopedHolder<'p, T, K, S> {
+    /// Inserts an `element` accessible by `key` into the local layer.
+    ///
+    /// Elements of the parent with the same `key` are overridden, but not replaced.
+    /// In case the `key` was already present in the local layer, the old `element` is returned and the new one is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// global.insert("a", 1);
+    ///
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// assert_eq!(scope.insert("a", 10), None);
+    /// assert_eq!(scope.insert("a", 25), Some(&10));
+    /// assert_eq!(global.get("a"), Some(&1));
+    /// ```
+    pub fn insert<Q>(&self, key: &Q, element: T) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.local.insert(key, element)
+    }
+
+    /// Inserts an `element`, which is created by a closure and can be accessed by `key`, into the local layer.
+    ///
+    /// In case the `key` was already present in the local layer, the old `element` is returned and the closure is not called.
+    /// Just like with [`Holder::insert_fn`](struct.Holder.html#method.insert_fn), the closure itself may use this `ScopedHolder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// global.insert("a", 1);
+    ///
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// assert_eq!(scope.insert_fn("a", || 10), None);
+    /// assert_eq!(scope.insert_fn("a", || unreachable!()), Some(&10));
+    /// ```
+    pub fn insert_fn<Q, F>(&self, key: &Q, element: F) -> Option<&T>
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.local.insert_fn(key, element)
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an element created by
+    /// the closure into the local layer in case the `key` is neither present in the local layer nor in the parent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let global = holder::Holder::new();
+    /// global.insert("font", 1);
+    ///
+    /// let scope = holder::ScopedHolder::new(&global);
+    /// assert_eq!(scope.get_or_insert_with("font", || unreachable!()), &1);
+    /// assert_eq!(scope.get_or_insert_with("boss", || 2), &2);
+    /// assert_eq!(scope.len(), 1);
+    /// ```
+    pub fn get_or_insert_with<Q, F>(&self, key: &Q, element: F) -> &T
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.get(key) {
+            Some(existing) => existing,
+            None => self.local.get_or_insert_with(key, element),
+        }
+    }
+}
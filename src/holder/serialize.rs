@@ -0,0 +1,109 @@
+//! `Serialize` and `Deserialize` implementations for `Holder<T>`, only available with the `serde` feature.
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use super::{Entry, Holder};
+
+/// The maximum capacity reserved before deserializing, as the size hint of the input can not be trusted.
+const MAX_PREALLOCATED: usize = 4096;
+
+impl<T: ?Sized + Serialize, K: Serialize + Eq + Hash, S: BuildHasher> Serialize for Holder<T, K, S> {
+    /// Serializes the `Holder<T>` as a map from keys to elements.
+    ///
+    /// Just like with `HashMap`, the order of the entries is arbitrary. Ids are not serialized.
+    ///
+    /// Requires the `serde` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate crow_util;
+    /// extern crate serde_json;
+    ///
+    /// use crow_util::holder;
+    ///
+    /// # fn main() {
+    /// let holder = holder::Holder::new();
+    /// holder.insert("grass", 1);
+    ///
+    /// let json = serde_json::to_string(&holder).unwrap();
+    /// assert_eq!(json, r#"{"grass":1}"#);
+    ///
+    /// let loaded: holder::Holder<u32> = serde_json::from_str(&json).unwrap();
+    /// assert_eq!(loaded.get("grass"), Some(&1));
+    /// # }
+    /// ```
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, element) in self {
+            map.serialize_entry(key, element)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, T, K, S> Deserialize<'de> for Holder<T, K, S>
+where T: ?Sized,
+      Box<T>: Deserialize<'de>,
+      K: Deserialize<'de> + Eq + Hash,
+      S: BuildHasher + Default {
+    /// Deserializes a `Holder<T>` from a map, which also works for unsized elements like `str` or `[T]`.
+    ///
+    /// Just like with `HashMap`, the last element of a duplicate key is kept.
+    ///
+    /// Requires the `serde` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate crow_util;
+    /// extern crate serde_json;
+    ///
+    /// use crow_util::holder;
+    ///
+    /// # fn main() {
+    /// let json = r#"{"greeting": "hello", "farewell": "bye", "greeting": "hi"}"#;
+    /// let holder: holder::Holder<str> = serde_json::from_str(json).unwrap();
+    ///
+    /// assert_eq!(holder.len(), 2);
+    /// assert_eq!(holder.get("greeting"), Some("hi"));
+    /// # }
+    /// ```
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(HolderVisitor {
+            _marker: PhantomData,
+        })
+    }
+}
+
+struct HolderVisitor<T: ?Sized, K, S> {
+    _marker: PhantomData<Holder<T, K, S>>,
+}
+
+impl<'de, T, K, S> Visitor<'de> for HolderVisitor<T, K, S>
+where T: ?Sized,
+      Box<T>: Deserialize<'de>,
+      K: Deserialize<'de> + Eq + Hash,
+      S: BuildHasher + Default {
+    type Value = Holder<T, K, S>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+        let capacity = map.size_hint().unwrap_or(0).min(MAX_PREALLOCATED);
+        let mut holder = Holder::with_capacity_and_hasher(capacity, S::default());
+        while let Some((key, element)) = map.next_entry::<K, Box<T>>()? {
+            holder.remove_boxed(&key);
+            if let Entry::Vacant(entry) = holder.entry(key) {
+                entry.insert_boxed(element);
+            }
+        }
+        Ok(holder)
+    }
+}
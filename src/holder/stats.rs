@@ -0,0 +1,177 @@
+//! Lookup statistics of `Holder<T>`, only available with the `stats` feature.
+use std::borrow::Borrow;
+use std::cell::Cell;
+use std::hash::{BuildHasher, Hash};
+
+use super::{Holder, Query, Slot};
+
+/// A snapshot of the lookup statistics of a [`Holder<T>`](struct.Holder.html).
+///
+/// This struct is created by [`Holder::stats`](struct.Holder.html#method.stats) and requires the `stats` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HolderStats {
+    /// The number of lookups which found an element.
+    pub hits: usize,
+    /// The number of lookups which did not find an element.
+    pub misses: usize,
+    /// The number of insertions which added a new element.
+    pub inserted: usize,
+    /// The number of insertions which found an already existing element.
+    pub existing: usize,
+    /// The number of times a closure passed to methods like `insert_fn` was called.
+    pub constructed: usize,
+}
+
+/// The counters of a `Holder<T>` which are not stored per element.
+#[derive(Default)]
+pub(super) struct Counters {
+    hits: Cell<usize>,
+    misses: Cell<usize>,
+    inserted: Cell<usize>,
+    existing: Cell<usize>,
+    constructed: Cell<usize>,
+}
+
+impl Counters {
+    pub(super) fn record_lookup<T: ?Sized>(&self, slot: Option<&Slot<T>>) {
+        match slot {
+            Some(slot) => {
+                increment(&slot.hits);
+                increment(&self.hits);
+            }
+            None => increment(&self.misses),
+        }
+    }
+
+    pub(super) fn record_insert(&self, inserted: bool) {
+        if inserted {
+            increment(&self.inserted);
+        } else {
+            increment(&self.existing);
+        }
+    }
+
+    pub(super) fn record_construction(&self) {
+        increment(&self.constructed);
+    }
+}
+
+fn increment(counter: &Cell<usize>) {
+    counter.set(counter.get().wrapping_add(1));
+}
+
+impl<T: ?Sized, K: Eq + Hash, S: BuildHasher> Holder<T, K, S> {
+    /// Returns the lookup statistics collected since the `Holder<T>` was created or [`reset_stats`](#method.reset_stats) was called.
+    ///
+    /// Lookups are counted by [`get`](#method.get) and indexing, insertions by every method which inserts
+    /// elements using `&self`, including `extend`. Requires the `stats` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    /// holder.insert("a", 25);
+    /// holder.insert_fn("b", || 7);
+    /// holder.insert_fn("b", || unreachable!());
+    ///
+    /// holder.get("a");
+    /// holder.get("a");
+    /// holder.get("c");
+    ///
+    /// let stats = holder.stats();
+    /// assert_eq!(stats.hits, 2);
+    /// assert_eq!(stats.misses, 1);
+    /// assert_eq!(stats.inserted, 2);
+    /// assert_eq!(stats.existing, 2);
+    /// assert_eq!(stats.constructed, 1);
+    /// ```
+    pub fn stats(&self) -> HolderStats {
+        HolderStats {
+            hits: self.counters.hits.get(),
+            misses: self.counters.misses.get(),
+            inserted: self.counters.inserted.get(),
+            existing: self.counters.existing.get(),
+            constructed: self.counters.constructed.get(),
+        }
+    }
+
+    /// Returns the number of successful lookups of the element corresponding to the key, or `None` in case the `key` is not present.
+    ///
+    /// Requires the `stats` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder.hits("a"), Some(0));
+    ///
+    /// holder.get("a");
+    /// assert_eq!(holder.hits("a"), Some(1));
+    /// assert_eq!(holder.hits("b"), None);
+    /// ```
+    pub fn hits<Q>(&self, key: &Q) -> Option<usize>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let items = unsafe {& *self.items.get() };
+        items.get(Query::new(key)).map(|slot| slot.hits.get())
+    }
+
+    /// Returns the number of successful lookups of each key, in arbitrary order.
+    ///
+    /// This can be used to find hot elements or elements which are never used. Requires the `stats` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("used", 1);
+    /// holder.insert("unused", 2);
+    /// holder.get("used");
+    ///
+    /// let unused: Vec<_> = holder.hits_by_key().into_iter()
+    ///     .filter(|&(_, hits)| hits == 0)
+    ///     .map(|(key, _)| key.as_str())
+    ///     .collect();
+    /// assert_eq!(unused, ["unused"]);
+    /// ```
+    pub fn hits_by_key(&self) -> Vec<(&K, usize)> {
+        let items = unsafe {& *self.items.get() };
+        items.iter().map(|(key, slot)| (key.get(), slot.hits.get())).collect()
+    }
+
+    /// Resets all lookup statistics, including the hits of each key, to zero.
+    ///
+    /// Requires the `stats` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("a", 42);
+    /// holder.get("a");
+    ///
+    /// holder.reset_stats();
+    /// assert_eq!(holder.stats(), holder::HolderStats::default());
+    /// assert_eq!(holder.hits("a"), Some(0));
+    /// ```
+    pub fn reset_stats(&self) {
+        let items = unsafe {& *self.items.get() };
+        for slot in items.values() {
+            slot.hits.set(0);
+        }
+
+        let counters = &self.counters;
+        for counter in &[&counters.hits, &counters.misses, &counters.inserted, &counters.existing, &counters.constructed] {
+            counter.set(0);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Suggestions of similar keys in case a lookup of `Holder<T>` fails.
+use std::borrow::Borrow;
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+use super::Holder;
+
+/// The maximum number of suggestions returned by `Holder::lookup`.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The error returned by [`Holder::lookup`](struct.Holder.html#method.lookup) in case no element was found.
+///
+/// Contains the existing keys which are most similar to the missing key, which is useful to detect typos.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let holder = holder::Holder::new();
+/// holder.insert("player", 42);
+///
+/// let error = holder.lookup("plyer").unwrap_err();
+/// assert_eq!(error.to_string(), "no asset 'plyer', did you mean 'player'?");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupError {
+    key: String,
+    suggestions: Vec<String>,
+}
+
+impl LookupError {
+    /// Returns the key which was not found.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the existing keys which are most similar to the missing key, the most similar key first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("player", 0);
+    /// holder.insert("players", 1);
+    /// holder.insert("enemy", 2);
+    ///
+    /// let error = holder.lookup("plyer").unwrap_err();
+    /// assert_eq!(error.key(), "plyer");
+    /// assert_eq!(error.suggestions(), ["player", "players"]);
+    /// ```
+    pub fn suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no asset '{}'", self.key)?;
+        if let Some((last, rest)) = self.suggestions.split_last() {
+            f.write_str(", did you mean ")?;
+            for (i, suggestion) in rest.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "'{}'", suggestion)?;
+            }
+            if !rest.is_empty() {
+                f.write_str(" or ")?;
+            }
+            write!(f, "'{}'?", last)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for LookupError {}
+
+impl<T: ?Sized, K: Eq + Hash + Borrow<str>, S: BuildHasher> Holder<T, K, S> {
+    /// Returns a reference to the element corresponding to the key, or an error containing similar keys in case it is not present.
+    ///
+    /// Keys are considered similar if at most a third of the characters have to be inserted, removed or replaced
+    /// to get the missing key. At most three keys are suggested. As all keys have to be compared,
+    /// a failed lookup is a lot slower than [`get`](#method.get).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::Holder::new();
+    /// holder.insert("player", 42);
+    /// holder.insert("prayer", 7);
+    ///
+    /// assert_eq!(holder.lookup("player"), Ok(&42));
+    ///
+    /// let error = holder.lookup("plyer").unwrap_err();
+    /// assert_eq!(error.to_string(), "no asset 'plyer', did you mean 'player' or 'prayer'?");
+    ///
+    /// let error = holder.lookup("enemy").unwrap_err();
+    /// assert_eq!(error.to_string(), "no asset 'enemy'");
+    /// ```
+    pub fn lookup(&self, key: &str) -> Result<&T, LookupError> {
+        match self.get(key) {
+            Some(element) => Ok(element),
+            None => Err(LookupError {
+                key: key.to_owned(),
+                suggestions: self.suggestions(key),
+            }),
+        }
+    }
+
+    /// Returns the keys which are most similar to `key`, the most similar key first.
+    fn suggestions(&self, key: &str) -> Vec<String> {
+        let max_distance = key.chars().count().div_ceil(3);
+        let items = unsafe {& *self.items.get() };
+        let mut similar: Vec<(usize, &str)> = items.keys()
+            .map(|existing| existing.get().borrow())
+            .map(|existing| (edit_distance(key, existing), existing))
+            .filter(|&(distance, _)| distance <= max_distance)
+            .collect();
+
+        similar.sort_unstable();
+        similar.into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, existing)| existing.to_owned())
+            .collect()
+    }
+}
+
+/// Returns the number of characters which have to be inserted, removed or replaced to change `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // the distances between the prefix of `a` visited so far and each prefix of `b`.
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let replace = previous[j] + if a == b { 0 } else { 1 };
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
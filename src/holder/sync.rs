@@ -0,0 +1,390 @@
+//! A thread safe version of `Holder<T>`.
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::cell::RefCell;
+use std::hash::{BuildHasher, Hash};
+use std::thread;
+
+#[cfg(not(loom))]
+use std::sync::{OnceLock, RwLock};
+#[cfg(not(loom))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(loom)]
+use loom::sync::RwLock;
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(loom)]
+use self::shim::OnceLock;
+
+#[cfg(not(loom))]
+thread_local! {
+    /// The addresses of the slots whose elements are currently being constructed by this thread.
+    static CONSTRUCTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(loom)]
+loom::thread_local! {
+    static CONSTRUCTING: RefCell<Vec<usize>> = RefCell::new(Vec::new());
+}
+
+/// A thread safe Hashmap which allows for immutable access while still allowing the addition of new objects.
+///
+/// `SyncHolder<T>` has the same append-only contract as [`Holder<T>`](struct.Holder.html), meaning that elements can
+/// be accessed and inserted through `&self` from many threads at once, and references stay valid for the lifetime of the holder.
+///
+/// The elements are split into multiple shards, each protected by its own `RwLock`, which is only held while looking up
+/// or inserting a key and never while an element is constructed.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+/// use std::thread;
+///
+/// let holder = holder::SyncHolder::new();
+///
+/// thread::scope(|s| {
+///     for i in 0..4 {
+///         let holder = &holder;
+///         s.spawn(move || {
+///             for j in 0..100 {
+///                 holder.insert(&format!("{}", j), i);
+///             }
+///         });
+///     }
+/// });
+///
+/// assert_eq!(holder.len(), 100);
+/// assert!(holder.get("42").is_some());
+/// ```
+pub struct SyncHolder<T, K = String> {
+    hasher: RandomState,
+    shards: Vec<RwLock<HashMap<K, Box<OnceLock<T>>>>>,
+    len: AtomicUsize,
+}
+
+impl<T, K: Eq + Hash> SyncHolder<T, K> {
+    /// Constructs a new, empty `SyncHolder<T>`, using a number of shards depending on the available parallelism.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::SyncHolder<u32> = holder::SyncHolder::new();
+    /// assert_eq!(holder.len(), 0);
+    /// ```
+    pub fn new() -> Self {
+        let parallelism = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(parallelism * 4)
+    }
+
+    /// Constructs a new, empty `SyncHolder<T>` which uses at least `shards` shards.
+    ///
+    /// More shards reduce contention between threads, while requiring slightly more memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::SyncHolder::with_shards(1);
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder.get("a"), Some(&42));
+    /// ```
+    pub fn with_shards(shards: usize) -> Self {
+        SyncHolder {
+            hasher: RandomState::new(),
+            shards: (0..shards.max(1).next_power_of_two()).map(|_| RwLock::new(HashMap::new())).collect(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns a reference to the element corresponding to the key.
+    ///
+    /// Elements which are currently being constructed by [`insert_fn`](#method.insert_fn) are not yet accessible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::SyncHolder::new();
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder.get("a"), Some(&42));
+    /// assert_eq!(holder.get("b"), None);
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let shard = self.shard(key).read().unwrap_or_else(|e| e.into_inner());
+        shard.get(key).and_then(|slot| unsafe { self.extend(slot) }.get())
+    }
+
+    /// Inserts an `element` accessible by `key`.
+    ///
+    /// In case the `key` was already present, the old `element` is returned and the new one is ignored.
+    /// In case the element corresponding to `key` is currently being constructed by another thread,
+    /// this method waits until it is finished.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::SyncHolder::new();
+    /// assert_eq!(holder.insert("a", 42), None);
+    /// assert_eq!(holder.insert("a", 25), Some(&42));
+    /// ```
+    pub fn insert<Q>(&self, key: &Q, element: T) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.insert_fn(key, || element)
+    }
+
+    /// Inserts an `element`, which is created by a closure and can be accessed by `key`.
+    /// In case the `key` was already present, the old `element` is returned and the closure is not called.
+    ///
+    /// The closure is called at most once per `key`, even if many threads try to insert the same `key` at once.
+    /// All other threads wait until the element is constructed and then return it.
+    /// The closure may insert other elements into this `SyncHolder<T>`.
+    ///
+    /// In case the closure panics, nothing is inserted and a later call may try again.
+    ///
+    /// # Panics
+    ///
+    /// Panics in case the closure inserts the same `key` again, as the element can not be constructed
+    /// before the closure returns. This would otherwise result in a deadlock.
+    ///
+    /// ```should_panic
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::SyncHolder::new();
+    /// holder.insert_fn("a", || *holder.get_or_insert_with("a", || 42));
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::thread;
+    ///
+    /// let holder = holder::SyncHolder::new();
+    /// let calls = AtomicUsize::new(0);
+    ///
+    /// thread::scope(|s| {
+    ///     for _ in 0..8 {
+    ///         s.spawn(|| {
+    ///             for key in 0..1000_u32 {
+    ///                 holder.insert_fn(&key, || {
+    ///                     calls.fetch_add(1, Ordering::Relaxed);
+    ///                     key * 2
+    ///                 });
+    ///             }
+    ///         });
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(calls.load(Ordering::Relaxed), 1000);
+    /// assert_eq!(holder.len(), 1000);
+    /// assert_eq!(holder.get(&21), Some(&42));
+    /// ```
+    pub fn insert_fn<Q, F>(&self, key: &Q, element: F) -> Option<&T>
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        match self.insert_full(key, element) {
+            (existing, false) => Some(existing),
+            (_, true) => None,
+        }
+    }
+
+    /// Returns a reference to the element corresponding to `key`, inserting an element created by
+    /// the closure in case the `key` was not present.
+    ///
+    /// Just like with [`insert_fn`](#method.insert_fn), the closure is called at most once per `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::SyncHolder::new();
+    /// assert_eq!(holder.get_or_insert_with("a", || 42), &42);
+    /// assert_eq!(holder.get_or_insert_with("a", || 25), &42);
+    /// ```
+    pub fn get_or_insert_with<Q, F>(&self, key: &Q, element: F) -> &T
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.insert_full(key, element).0
+    }
+
+    /// Clears the map, removing all `key`-`element` pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::SyncHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.clear();
+    /// assert!(holder.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        for shard in &mut self.shards {
+            shard.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+        self.len.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// Elements which are currently being constructed are not counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::SyncHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.insert("b", 360);
+    /// assert_eq!(holder.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::SyncHolder::new();
+    /// assert!(holder.is_empty());
+    /// holder.insert("a", 42);
+    /// assert!(!holder.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn shard<Q: ?Sized + Hash>(&self, key: &Q) -> &RwLock<HashMap<K, Box<OnceLock<T>>>> {
+        let hash = self.hasher.hash_one(key) as usize;
+        &self.shards[hash & (self.shards.len() - 1)]
+    }
+
+    /// Extends the lifetime of `slot` to the lifetime of `self`.
+    ///
+    /// This is safe as long as `slot` is owned by `self`, as slots are boxed and only removed using `&mut self`.
+    unsafe fn extend<'a>(&'a self, slot: &OnceLock<T>) -> &'a OnceLock<T> {
+        &*(slot as *const OnceLock<T>)
+    }
+
+    /// Returns a reference to the element corresponding to `key` and whether `element` was called.
+    fn insert_full<Q, F>(&self, key: &Q, element: F) -> (&T, bool)
+    where F: FnOnce() -> T,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        let lock = self.shard(key);
+        let existing = lock.read().unwrap_or_else(|e| e.into_inner()).get(key).map(|slot| unsafe { self.extend(slot) });
+        let slot = match existing {
+            Some(slot) => slot,
+            None => {
+                let mut shard = lock.write().unwrap_or_else(|e| e.into_inner());
+                let slot = shard.entry(key.to_owned()).or_insert_with(|| Box::new(OnceLock::new()));
+                unsafe { self.extend(slot) }
+            }
+        };
+
+        if let Some(existing) = slot.get() {
+            return (existing, false);
+        }
+
+        let address = slot as *const OnceLock<T> as usize;
+        if CONSTRUCTING.with(|constructing| constructing.borrow().contains(&address)) {
+            panic!("the element of a key was inserted while it was constructed by the same thread");
+        }
+
+        let mut called = false;
+        let element = slot.get_or_init(|| {
+            called = true;
+            CONSTRUCTING.with(|constructing| constructing.borrow_mut().push(address));
+            // removes the address once the element is constructed, even if `element` panics.
+            let _guard = ConstructingGuard;
+            element()
+        });
+
+        if called {
+            self.len.fetch_add(1, Ordering::AcqRel);
+        }
+        (element, called)
+    }
+}
+
+impl<T, K: Eq + Hash> Default for SyncHolder<T, K> {
+    /// Creates an empty `SyncHolder<T>`.
+    fn default() -> Self {
+        SyncHolder::new()
+    }
+}
+
+/// Removes the most recent address from `CONSTRUCTING` when dropped.
+struct ConstructingGuard;
+
+impl Drop for ConstructingGuard {
+    fn drop(&mut self) {
+        CONSTRUCTING.with(|constructing| constructing.borrow_mut().pop());
+    }
+}
+
+/// Loom does not provide a `OnceLock`, so a simple version of it is built from loom primitives.
+#[cfg(loom)]
+mod shim {
+    use loom::cell::UnsafeCell;
+    use loom::sync::Mutex;
+    use loom::sync::atomic::{AtomicBool, Ordering};
+
+    pub struct OnceLock<T> {
+        initialized: AtomicBool,
+        lock: Mutex<()>,
+        value: UnsafeCell<Option<T>>,
+    }
+
+    unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+    unsafe impl<T: Send> Send for OnceLock<T> {}
+
+    impl<T> OnceLock<T> {
+        pub fn new() -> Self {
+            OnceLock {
+                initialized: AtomicBool::new(false),
+                lock: Mutex::new(()),
+                value: UnsafeCell::new(None),
+            }
+        }
+
+        pub fn get(&self) -> Option<&T> {
+            if self.initialized.load(Ordering::Acquire) {
+                self.value.with(|value| unsafe { (*value).as_ref() })
+            }
+            else {
+                None
+            }
+        }
+
+        pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+            if let Some(value) = self.get() {
+                return value;
+            }
+
+            let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+            if !self.initialized.load(Ordering::Acquire) {
+                let value = f();
+                self.value.with_mut(|slot| unsafe { *slot = Some(value) });
+                self.initialized.store(true, Ordering::Release);
+            }
+            self.get().unwrap()
+        }
+    }
+}
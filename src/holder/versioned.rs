@@ -0,0 +1,300 @@
+//! A version of `Holder<T>` whose elements can be replaced while they are still borrowed.
+use std::borrow::Borrow;
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::StableBox;
+
+/// A Hashmap which allows for immutable access while still allowing the addition and replacement of objects.
+///
+/// Each element has a version number, starting at `0` when it is first inserted and increasing by one with each replacement.
+/// Replaced versions are kept alive, meaning that existing references stay valid,
+/// until they are explicitly dropped using [`collect_garbage`](#method.collect_garbage).
+///
+/// This is useful for hot reloading, where a new version of an asset is loaded while the old one is still in use.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::holder;
+///
+/// let mut holder = holder::VersionedHolder::new();
+/// holder.insert("texture", "old pixels");
+///
+/// {
+///     let current_frame = holder.get("texture").unwrap();
+///     assert_eq!(holder.replace("texture", "new pixels"), 1);
+///
+///     assert_eq!(*current_frame, "old pixels");
+///     assert_eq!(holder.get("texture"), Some(&"new pixels"));
+/// }
+///
+/// // between frames, no references are in use anymore.
+/// assert_eq!(holder.collect_garbage(), 1);
+/// assert_eq!(holder.get_version("texture", 0), None);
+/// ```
+pub struct VersionedHolder<T, K = String> {
+    items: UnsafeCell<HashMap<K, Versions<T>>>,
+}
+
+impl<T, K: Eq + Hash> VersionedHolder<T, K> {
+    /// Constructs a new, empty `VersionedHolder<T>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder: holder::VersionedHolder<u32> = holder::VersionedHolder::new();
+    /// assert_eq!(holder.len(), 0);
+    /// ```
+    pub fn new() -> Self {
+        VersionedHolder {
+            items: UnsafeCell::new(HashMap::new()),
+        }
+    }
+
+    /// Constructs a new, empty `VersionedHolder<T>` with the specified capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::VersionedHolder::with_capacity(42);
+    /// holder.insert("a", 7);
+    /// assert_eq!(holder.get("a"), Some(&7));
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        VersionedHolder {
+            items: UnsafeCell::new(HashMap::with_capacity(capacity)),
+        }
+    }
+
+    /// Returns a reference to the newest version of the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::VersionedHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.replace("a", 43);
+    /// assert_eq!(holder.get("a"), Some(&43));
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.get_versions(key).map(|versions| versions.newest())
+    }
+
+    /// Returns a reference to a specific version of the element corresponding to the key.
+    ///
+    /// Returns `None` in case this version does not exist or was already dropped by [`collect_garbage`](#method.collect_garbage).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::VersionedHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.replace("a", 43);
+    /// assert_eq!(holder.get_version("a", 0), Some(&42));
+    /// assert_eq!(holder.get_version("a", 1), Some(&43));
+    /// assert_eq!(holder.get_version("a", 2), None);
+    /// ```
+    pub fn get_version<Q>(&self, key: &Q, version: u64) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let versions = self.get_versions(key)?;
+        let index = version.checked_sub(versions.first)?;
+        versions.elements.get(index as usize).map(|element| element.get())
+    }
+
+    /// Returns the number of the newest version of the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::VersionedHolder::new();
+    /// assert_eq!(holder.version("a"), None);
+    /// holder.insert("a", 42);
+    /// assert_eq!(holder.version("a"), Some(0));
+    /// holder.replace("a", 43);
+    /// assert_eq!(holder.version("a"), Some(1));
+    /// ```
+    pub fn version<Q>(&self, key: &Q) -> Option<u64>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.get_versions(key).map(|versions| versions.newest_version())
+    }
+
+    /// Inserts an `element` accessible by `key`.
+    ///
+    /// In case the `key` was already present, the newest version of the old `element` is returned and the new one is ignored.
+    /// Use [`replace`](#method.replace) to publish a new version instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::VersionedHolder::new();
+    /// assert_eq!(holder.insert("a", 42), None);
+    /// assert_eq!(holder.insert("a", 25), Some(&42));
+    /// ```
+    pub fn insert<Q>(&self, key: &Q, element: T) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        if let Some(existing) = self.get(key) {
+            return Some(existing);
+        }
+
+        let items = unsafe {&mut *self.items.get() };
+        items.insert(key.to_owned(), Versions::new(element));
+        None
+    }
+
+    /// Publishes `element` as the newest version of the element corresponding to `key`, returning its version number.
+    ///
+    /// All references to older versions stay valid. In case the `key` was not present, `element` is inserted as version `0`.
+    /// This method can be used while `VersionedHolder<T>` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::VersionedHolder::new();
+    /// assert_eq!(holder.replace("a", 42), 0);
+    ///
+    /// let old = holder.get("a").unwrap();
+    /// assert_eq!(holder.replace("a", 43), 1);
+    /// assert_eq!(old, &42);
+    /// ```
+    pub fn replace<Q>(&self, key: &Q, element: T) -> u64
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        let items = unsafe {&mut *self.items.get() };
+        if let Some(versions) = items.get_mut(key) {
+            versions.elements.push(StableBox::new(element));
+            return versions.newest_version();
+        }
+
+        items.insert(key.to_owned(), Versions::new(element));
+        0
+    }
+
+    /// Drops every version except for the newest one of each element, returning the number of dropped versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::VersionedHolder::new();
+    /// holder.insert("a", 1);
+    /// holder.replace("a", 2);
+    /// holder.replace("a", 3);
+    ///
+    /// assert_eq!(holder.collect_garbage(), 2);
+    /// assert_eq!(holder.get("a"), Some(&3));
+    /// assert_eq!(holder.version("a"), Some(2));
+    /// ```
+    pub fn collect_garbage(&mut self) -> usize {
+        let mut dropped = 0;
+        for versions in self.items.get_mut().values_mut() {
+            let old = versions.elements.len() - 1;
+            versions.elements.drain(..old);
+            versions.first += old as u64;
+            dropped += old;
+        }
+        dropped
+    }
+
+    /// Clears the map, removing all elements and their versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let mut holder = holder::VersionedHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.clear();
+    /// assert!(holder.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.items.get_mut().clear();
+    }
+
+    /// Returns the number of elements in the map, not counting old versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::VersionedHolder::new();
+    /// holder.insert("a", 42);
+    /// holder.replace("a", 43);
+    /// holder.insert("b", 360);
+    /// assert_eq!(holder.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        unsafe { & *self.items.get() }.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::holder;
+    ///
+    /// let holder = holder::VersionedHolder::new();
+    /// assert!(holder.is_empty());
+    /// holder.insert("a", 42);
+    /// assert!(!holder.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        unsafe { & *self.items.get() }.is_empty()
+    }
+
+    fn get_versions<Q>(&self, key: &Q) -> Option<&Versions<T>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        let items = unsafe {& *self.items.get() };
+        items.get(key)
+    }
+}
+
+impl<T, K: Eq + Hash> Default for VersionedHolder<T, K> {
+    /// Creates an empty `VersionedHolder<T>`.
+    fn default() -> Self {
+        VersionedHolder::new()
+    }
+}
+
+/// All versions of a single element which were not yet dropped.
+struct Versions<T> {
+    /// The version number of `elements[0]`.
+    first: u64,
+    elements: Vec<StableBox<T>>,
+}
+
+impl<T> Versions<T> {
+    fn new(element: T) -> Self {
+        Versions {
+            first: 0,
+            elements: vec![StableBox::new(element)],
+        }
+    }
+
+    fn newest(&self) -> &T {
+        self.elements[self.elements.len() - 1].get()
+    }
+
+    fn newest_version(&self) -> u64 {
+        self.first + self.elements.len() as u64 - 1
+    }
+}
@@ -1,14 +1,25 @@
 //! The utility crate for the [`crow_engine`].
 //!
-//! # TODO:
-//!
-//! * impl [`Iterator`] for [`Holder<T>`].
+//! The most important types of this crate are [`Holder<T>`] and [`SelfRefHolder<T,U>`], which allow for
+//! immutable access to their elements while still being able to insert new elements.
+//! Files from disk can be loaded into such a holder using an [`AssetManager`].
 //!
 //! [`crow_engine`]:https://crates.io/crates/crow_engine
 //! [`Holder<T>`]: holder/struct.Holder.html
-//! [`Iterator`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html
 //! [`SelfRefHolder<T,U>`]: self_ref/struct.SelfRefHolder.html
+//! [`AssetManager`]: assets/struct.AssetManager.html
+
+#[cfg(feature = "ahash")]
+extern crate ahash;
+#[cfg(loom)]
+extern crate loom;
+#[cfg(feature = "fxhash")]
+extern crate rustc_hash;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 pub mod holder;
 pub mod traits;
 pub mod pop_iter;
+pub mod self_ref;
+pub mod assets;
@@ -49,11 +49,11 @@ impl<'a, T> Iterator for PopIter<'a, T>{
 
 /// Conversion into a `PopIter`.
 pub trait ToPopIter<T> {
-    fn pop_iter(&mut self) -> PopIter<T>;
+    fn pop_iter(&mut self) -> PopIter<'_, T>;
 }
 
 impl<T> ToPopIter<T> for Vec<T> {
-    fn pop_iter(&mut self) -> PopIter<T> {
+    fn pop_iter(&mut self) -> PopIter<'_, T> {
         PopIter {
             vec: self,
         }
@@ -0,0 +1,358 @@
+//! A module containing a holder whose elements can borrow from owned data stored in the same holder.
+//!
+//! For examples and further explanation, visit [`SelfRefHolder<T, U>`](struct.SelfRefHolder.html).
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr::{self, NonNull};
+
+use holder::Holder;
+
+/// Describes a type which borrows from data owned by a [`SelfRefHolder<T, U>`](struct.SelfRefHolder.html).
+///
+/// This trait is implemented by a marker type, with `Ref<'a>` being the actual borrowing type.
+/// The only possible implementation of [`shorten`](#tymethod.shorten) is to return its argument,
+/// which only compiles in case `Ref<'a>` is covariant over `'a`.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::self_ref::Dependent;
+///
+/// struct SpriteSheet<'a> {
+///     header: &'a [u8],
+///     pixels: &'a [u8],
+/// }
+///
+/// struct SpriteSheetRef;
+///
+/// impl Dependent for SpriteSheetRef {
+///     type Ref<'a> = SpriteSheet<'a>;
+///
+///     fn shorten<'a, 'b: 'a>(long: &'a SpriteSheet<'b>) -> &'a SpriteSheet<'a> {
+///         long
+///     }
+/// }
+/// ```
+pub trait Dependent {
+    /// The type borrowing from data which lives for `'a`.
+    type Ref<'a>;
+
+    /// Shortens the lifetime of the borrowed data.
+    fn shorten<'a, 'b: 'a>(long: &'a Self::Ref<'b>) -> &'a Self::Ref<'a>;
+}
+
+/// A Hashmap which stores owned data of type `T` next to elements of type `U::Ref<'_>` borrowing from it.
+///
+/// Just like [`Holder<T>`](../holder/struct.Holder.html), new elements can be inserted while the holder is already
+/// immutably borrowed. Each element is created by a closure which receives a reference to its owner,
+/// which is never moved or dropped before the element itself.
+///
+/// # Examples
+///
+/// ```
+/// use crow_util::self_ref::{Dependent, SelfRefHolder};
+///
+/// struct SpriteSheet<'a> {
+///     name: &'a str,
+///     pixels: &'a [u8],
+/// }
+///
+/// struct SpriteSheetRef;
+///
+/// impl Dependent for SpriteSheetRef {
+///     type Ref<'a> = SpriteSheet<'a>;
+///
+///     fn shorten<'a, 'b: 'a>(long: &'a SpriteSheet<'b>) -> &'a SpriteSheet<'a> {
+///         long
+///     }
+/// }
+///
+/// fn parse(bytes: &[u8]) -> SpriteSheet<'_> {
+///     let len = bytes[0] as usize;
+///     SpriteSheet {
+///         name: std::str::from_utf8(&bytes[1..len + 1]).unwrap(),
+///         pixels: &bytes[len + 1..],
+///     }
+/// }
+///
+/// let holder: SelfRefHolder<Vec<u8>, SpriteSheetRef> = SelfRefHolder::new();
+/// holder.insert("player", b"\x06player\x01\x02\x03".to_vec(), |bytes| parse(bytes));
+///
+/// let sheet = holder.get("player").unwrap();
+/// assert_eq!(sheet.name, "player");
+/// assert_eq!(sheet.pixels, [1, 2, 3]);
+///
+/// // inserting new elements keeps previous references valid.
+/// holder.insert("enemy", b"\x05enemy\x04".to_vec(), |bytes| parse(bytes));
+/// assert_eq!(sheet.pixels, [1, 2, 3]);
+/// assert_eq!(holder.get_owner("enemy").map(|v| v.len()), Some(7));
+/// ```
+pub struct SelfRefHolder<T, U: Dependent, K = String> {
+    items: Holder<Entry<T, U::Ref<'static>>, K>,
+    _marker: PhantomData<U>,
+}
+
+impl<T, U: Dependent, K: Eq + Hash> SelfRefHolder<T, U, K> {
+    /// Constructs a new, empty `SelfRefHolder<T, U>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::self_ref::{Dependent, SelfRefHolder};
+    ///
+    /// struct StrRef;
+    ///
+    /// impl Dependent for StrRef {
+    ///     type Ref<'a> = &'a str;
+    ///
+    ///     fn shorten<'a, 'b: 'a>(long: &'a &'b str) -> &'a &'a str {
+    ///         long
+    ///     }
+    /// }
+    ///
+    /// let holder: SelfRefHolder<String, StrRef> = SelfRefHolder::new();
+    /// assert_eq!(holder.len(), 0);
+    /// ```
+    pub fn new() -> Self {
+        SelfRefHolder {
+            items: Holder::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Constructs a new, empty `SelfRefHolder<T, U>` with the specified capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::self_ref::{Dependent, SelfRefHolder};
+    ///
+    /// struct StrRef;
+    ///
+    /// impl Dependent for StrRef {
+    ///     type Ref<'a> = &'a str;
+    ///
+    ///     fn shorten<'a, 'b: 'a>(long: &'a &'b str) -> &'a &'a str {
+    ///         long
+    ///     }
+    /// }
+    ///
+    /// let holder: SelfRefHolder<String, StrRef> = SelfRefHolder::with_capacity(42);
+    /// assert!(holder.capacity() >= 42);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        SelfRefHolder {
+            items: Holder::with_capacity(capacity),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::self_ref::{Dependent, SelfRefHolder};
+    ///
+    /// struct StrRef;
+    ///
+    /// impl Dependent for StrRef {
+    ///     type Ref<'a> = &'a str;
+    ///
+    ///     fn shorten<'a, 'b: 'a>(long: &'a &'b str) -> &'a &'a str {
+    ///         long
+    ///     }
+    /// }
+    ///
+    /// let holder: SelfRefHolder<String, StrRef> = SelfRefHolder::new();
+    /// holder.insert("a", "key=value".to_string(), |s| s.split('=').nth(1).unwrap());
+    /// assert_eq!(holder.get("a"), Some(&"value"));
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<&U::Ref<'_>>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.items.get(key).map(|entry| U::shorten(&*entry.borrowed))
+    }
+
+    /// Returns a reference to the owned data of the element corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::self_ref::{Dependent, SelfRefHolder};
+    ///
+    /// struct StrRef;
+    ///
+    /// impl Dependent for StrRef {
+    ///     type Ref<'a> = &'a str;
+    ///
+    ///     fn shorten<'a, 'b: 'a>(long: &'a &'b str) -> &'a &'a str {
+    ///         long
+    ///     }
+    /// }
+    ///
+    /// let holder: SelfRefHolder<String, StrRef> = SelfRefHolder::new();
+    /// holder.insert("a", "key=value".to_string(), |s| &s[4..]);
+    /// assert_eq!(holder.get_owner("a").map(|s| &s[..]), Some("key=value"));
+    /// ```
+    pub fn get_owner<Q>(&self, key: &Q) -> Option<&T>
+    where K: Borrow<Q>, Q: ?Sized + Hash + Eq {
+        self.items.get(key).map(|entry| entry.owner.get())
+    }
+
+    /// Inserts the `owner` together with an element borrowing from it, which is created by the closure `borrow`.
+    ///
+    /// In case the `key` was already present, the old element is returned,
+    /// the new `owner` is dropped and the closure is not called.
+    /// This method can be used while `SelfRefHolder<T, U>` is already immutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::self_ref::{Dependent, SelfRefHolder};
+    ///
+    /// struct StrRef;
+    ///
+    /// impl Dependent for StrRef {
+    ///     type Ref<'a> = &'a str;
+    ///
+    ///     fn shorten<'a, 'b: 'a>(long: &'a &'b str) -> &'a &'a str {
+    ///         long
+    ///     }
+    /// }
+    ///
+    /// let holder: SelfRefHolder<String, StrRef> = SelfRefHolder::new();
+    /// assert_eq!(holder.insert("a", "hello world".to_string(), |s| &s[..5]), None);
+    /// assert_eq!(holder.insert("a", "goodbye".to_string(), |s| &s[..]), Some(&"hello"));
+    /// ```
+    pub fn insert<Q, F>(&self, key: &Q, owner: T, borrow: F) -> Option<&U::Ref<'_>>
+    where F: for<'a> FnOnce(&'a T) -> U::Ref<'a>,
+          K: Borrow<Q>, Q: ?Sized + Hash + Eq + ToOwned<Owned = K> {
+        self.items.insert_fn(key, || new_entry::<T, U, F>(owner, borrow))
+            .map(|entry| U::shorten(&*entry.borrowed))
+    }
+
+    /// Clears the map, removing all elements and their owners.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::self_ref::{Dependent, SelfRefHolder};
+    ///
+    /// struct StrRef;
+    ///
+    /// impl Dependent for StrRef {
+    ///     type Ref<'a> = &'a str;
+    ///
+    ///     fn shorten<'a, 'b: 'a>(long: &'a &'b str) -> &'a &'a str {
+    ///         long
+    ///     }
+    /// }
+    ///
+    /// let mut holder: SelfRefHolder<String, StrRef> = SelfRefHolder::new();
+    /// holder.insert("a", "hello".to_string(), |s| &s[..]);
+    /// holder.clear();
+    /// assert!(holder.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crow_util::self_ref::{Dependent, SelfRefHolder};
+    ///
+    /// struct StrRef;
+    ///
+    /// impl Dependent for StrRef {
+    ///     type Ref<'a> = &'a str;
+    ///
+    ///     fn shorten<'a, 'b: 'a>(long: &'a &'b str) -> &'a &'a str {
+    ///         long
+    ///     }
+    /// }
+    ///
+    /// let holder: SelfRefHolder<String, StrRef> = SelfRefHolder::new();
+    /// holder.insert("a", "hello".to_string(), |s| &s[..]);
+    /// assert_eq!(holder.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the number of elements the map can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.items.capacity()
+    }
+}
+
+impl<T, U: Dependent, K: Eq + Hash> Default for SelfRefHolder<T, U, K> {
+    /// Creates an empty `SelfRefHolder<T, U>`.
+    fn default() -> Self {
+        SelfRefHolder::new()
+    }
+}
+
+/// An owner together with the element borrowing from it.
+///
+/// `borrowed` is only valid as long as `owner` is alive, so it is dropped first.
+struct Entry<T, B> {
+    borrowed: ManuallyDrop<B>,
+    owner: Owner<T>,
+}
+
+unsafe impl<T: Send, B: Send> Send for Entry<T, B> {}
+
+/// Creates a new entry, erasing the lifetime of the element borrowing from `owner`.
+fn new_entry<T, U, F>(owner: T, borrow: F) -> Entry<T, U::Ref<'static>>
+where U: Dependent,
+      F: for<'a> FnOnce(&'a T) -> U::Ref<'a> {
+    let owner = Owner::new(owner);
+    let borrowed = ManuallyDrop::new(borrow(unsafe { &*owner.ptr.as_ptr() }));
+    // `owner` is neither moved nor dropped before `borrowed`, so erasing the lifetime is safe.
+    let borrowed = unsafe { ptr::read((&*borrowed as *const U::Ref<'_>).cast::<U::Ref<'static>>()) };
+    Entry {
+        borrowed: ManuallyDrop::new(borrowed),
+        owner,
+    }
+}
+
+impl<T, B> Drop for Entry<T, B> {
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.borrowed) }
+    }
+}
+
+/// Owned data stored in its own allocation.
+///
+/// A raw pointer is used instead of a `Box<T>`, as moving a `Box` asserts unique access to its content.
+struct Owner<T> {
+    ptr: NonNull<T>,
+}
+
+impl<T> Owner<T> {
+    fn new(owner: T) -> Self {
+        Owner {
+            ptr: unsafe { NonNull::new_unchecked(Box::into_raw(Box::new(owner))) },
+        }
+    }
+
+    fn get(&self) -> &T {
+        unsafe { &*self.ptr.as_ptr() }
+    }
+}
+
+impl<T> Drop for Owner<T> {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.ptr.as_ptr())) }
+    }
+}
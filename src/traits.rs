@@ -68,6 +68,10 @@ pub trait GetTwo<T> {
     /// Mutably borrows 2 elements at once without checking if it is safe to do so.TakeTwo
     /// 
     /// This is genarally not recommended, use with caution! For a safe alternative see [`get_two`](#tymethod.get_two)
+    ///
+    /// # Safety
+    ///
+    /// `index_a` and `index_b` must both be in bounds and must not be equal.
     /// 
     /// # Examples
     /// 
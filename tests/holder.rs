@@ -0,0 +1,58 @@
+//! Tests of `Holder` which insert elements while references into it are still alive.
+//!
+//! These tests are meant to be run with Miri as well: `cargo +nightly miri test --test holder`.
+extern crate crow_util;
+
+use crow_util::holder::Holder;
+
+/// Elements inserted while iterating are skipped, even if they reuse the slot of a removed element.
+#[test]
+fn iter_skips_elements_inserted_while_iterating() {
+    let mut holder = Holder::new();
+    for i in 0..8 {
+        holder.insert(&i.to_string(), i);
+    }
+    holder.remove("3");
+    holder.remove("5");
+
+    let mut visited = Vec::new();
+    for (key, element) in holder.iter() {
+        // enough insertions to rehash the map several times.
+        for j in 0..64 {
+            holder.insert(&format!("{}_{}", key, j), element * 100 + j);
+        }
+        visited.push(*element);
+    }
+
+    visited.sort();
+    assert_eq!(visited, [0, 1, 2, 4, 6, 7]);
+    assert_eq!(holder.len(), 6 + 6 * 64);
+    assert_eq!(holder.iter().len(), holder.len());
+    assert_eq!(holder.get("7_63"), Some(&763));
+}
+
+/// A reference returned before a re-entrant `insert_fn` must survive the rehash caused by the inner insertions.
+#[test]
+fn references_survive_rehash_during_reentrant_insert_fn() {
+    let holder = Holder::with_capacity(1);
+    let first = holder.get_or_insert_with("first", || vec![1, 2, 3]);
+    let capacity = holder.capacity();
+
+    let previous = holder.insert_fn("atlas", || {
+        let mut tiles = Vec::new();
+        for i in 0..256 {
+            let tile = holder.get_or_insert_with(&format!("atlas/{}", i), || vec![i]);
+            tiles.push(tile[0]);
+            // `first` is read while the map is rehashed by the inner insertions.
+            assert_eq!(first[2], 3);
+        }
+        tiles
+    });
+
+    assert_eq!(previous, None);
+    assert!(holder.capacity() > capacity);
+    let atlas = holder.get("atlas").unwrap();
+    assert_eq!(*first, [1, 2, 3]);
+    assert_eq!(atlas.len(), 256);
+    assert_eq!(holder.get("atlas/255"), Some(&vec![255]));
+}
@@ -0,0 +1,59 @@
+//! Model checks of `SyncHolder` using loom, which explores every interleaving of the threads.
+//!
+//! Run using `RUSTFLAGS="--cfg loom" cargo test --release --test loom`.
+#![cfg(loom)]
+
+extern crate crow_util;
+extern crate loom;
+
+use crow_util::holder::SyncHolder;
+use loom::sync::Arc;
+use loom::sync::atomic::{AtomicUsize, Ordering};
+use loom::thread;
+
+/// Two threads insert the same key, the constructor must run exactly once and both see its element.
+#[test]
+fn concurrent_insert_fn_constructs_once() {
+    loom::model(|| {
+        let holder = Arc::new(SyncHolder::with_shards(1));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let threads: Vec<_> = (0..2).map(|_| {
+            let (holder, calls) = (holder.clone(), calls.clone());
+            thread::spawn(move || {
+                *holder.get_or_insert_with("a", || {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    42
+                })
+            })
+        }).collect();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 42);
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(holder.len(), 1);
+    });
+}
+
+/// `get` racing `insert` either misses the key or sees the complete element.
+#[test]
+fn get_races_insert() {
+    loom::model(|| {
+        let holder = Arc::new(SyncHolder::with_shards(1));
+
+        let inserter = {
+            let holder = holder.clone();
+            thread::spawn(move || {
+                holder.insert("a", vec![1, 2, 3]);
+            })
+        };
+
+        if let Some(element) = holder.get("a") {
+            assert_eq!(*element, [1, 2, 3]);
+        }
+
+        inserter.join().unwrap();
+        assert_eq!(holder.get("a"), Some(&vec![1, 2, 3]));
+    });
+}
@@ -0,0 +1,110 @@
+//! Round trips of `Holder` through JSON, RON and bincode, only compiled with the `serde` feature.
+#![cfg(feature = "serde")]
+
+extern crate bincode;
+extern crate crow_util;
+extern crate ron;
+extern crate serde_json;
+
+use crow_util::holder::Holder;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Asserts that both holders contain the same keys and elements.
+fn assert_same<T: ?Sized + PartialEq + Debug, K: Eq + Hash + Debug>(left: &Holder<T, K>, right: &Holder<T, K>) {
+    assert_eq!(left.len(), right.len());
+    for (key, element) in left {
+        assert_eq!(Some(element), right.get(key), "different elements for {:?}", key);
+    }
+}
+
+fn tiles() -> Holder<u32> {
+    let holder = Holder::new();
+    for (i, name) in ["grass", "water", "sand", "stone", "lava"].iter().enumerate() {
+        holder.insert(*name, i as u32);
+    }
+    holder
+}
+
+fn localized() -> Holder<str> {
+    let holder = Holder::new();
+    holder.insert_boxed("menu/start", "Spiel starten".into());
+    holder.insert_boxed("menu/quit", "Beenden".into());
+    holder.insert_boxed("empty", "".into());
+    holder
+}
+
+#[test]
+fn json_round_trip() {
+    let holder = tiles();
+    let json = serde_json::to_string(&holder).unwrap();
+    assert_same(&serde_json::from_str::<Holder<u32>>(&json).unwrap(), &holder);
+
+    let holder = localized();
+    let json = serde_json::to_string(&holder).unwrap();
+    assert_same(&serde_json::from_str::<Holder<str>>(&json).unwrap(), &holder);
+}
+
+#[test]
+fn ron_round_trip() {
+    let holder = tiles();
+    let text = ron::to_string(&holder).unwrap();
+    assert_same(&ron::from_str::<Holder<u32>>(&text).unwrap(), &holder);
+
+    let holder = localized();
+    let text = ron::to_string(&holder).unwrap();
+    assert_same(&ron::from_str::<Holder<str>>(&text).unwrap(), &holder);
+}
+
+#[test]
+fn bincode_round_trip() {
+    let holder = tiles();
+    let bytes = bincode::serialize(&holder).unwrap();
+    assert_same(&bincode::deserialize::<Holder<u32>>(&bytes).unwrap(), &holder);
+
+    let holder = localized();
+    let bytes = bincode::serialize(&holder).unwrap();
+    assert_same(&bincode::deserialize::<Holder<str>>(&bytes).unwrap(), &holder);
+}
+
+#[test]
+fn unsized_slices_and_generic_keys() {
+    let holder: Holder<[u16], u32> = Holder::new();
+    holder.insert_boxed(&7, vec![1, 2, 3].into());
+    holder.insert_boxed(&9, Vec::new().into());
+
+    let bytes = bincode::serialize(&holder).unwrap();
+    let loaded: Holder<[u16], u32> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(loaded.get(&7), Some(&[1, 2, 3][..]));
+    assert_same(&loaded, &holder);
+
+    let json = serde_json::to_string(&holder).unwrap();
+    assert_same(&serde_json::from_str::<Holder<[u16], u32>>(&json).unwrap(), &holder);
+}
+
+#[test]
+fn key_order_does_not_matter() {
+    let forward: Holder<u32> = serde_json::from_str(r#"{"a": 1, "b": 2, "c": 3}"#).unwrap();
+    let backward: Holder<u32> = serde_json::from_str(r#"{"c": 3, "b": 2, "a": 1}"#).unwrap();
+    assert_same(&forward, &backward);
+
+    // the serialized order is arbitrary, but contains every entry exactly once.
+    let value: serde_json::Value = serde_json::to_value(&forward).unwrap();
+    let mut keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+    keys.sort();
+    assert_eq!(keys, ["a", "b", "c"]);
+}
+
+#[test]
+fn duplicate_keys_keep_the_last_element() {
+    let holder: Holder<str> = ron::from_str(r#"{"a": "first", "b": "other", "a": "last"}"#).unwrap();
+    assert_eq!(holder.len(), 2);
+    assert_eq!(holder.get("a"), Some("last"));
+    assert_eq!(holder.get_by_id(holder.id("a").unwrap()), Some("last"));
+}
+
+#[test]
+fn invalid_input_is_rejected() {
+    assert!(serde_json::from_str::<Holder<u32>>("[1, 2, 3]").is_err());
+    assert!(serde_json::from_str::<Holder<u32>>(r#"{"a": "text"}"#).is_err());
+}
@@ -0,0 +1,100 @@
+//! Stress tests for `SyncHolder`, which race many threads on the same keys.
+extern crate crow_util;
+
+use crow_util::holder::SyncHolder;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+use std::thread;
+
+const THREADS: usize = 16;
+const KEYS: usize = 256;
+const ROUNDS: usize = 20;
+
+/// Every thread visits all keys starting at a different offset, so each key is raced by all threads.
+#[test]
+fn get_or_insert_with_initializes_each_key_once() {
+    for shards in &[1, 4, 64] {
+        for _ in 0..ROUNDS {
+            let holder = SyncHolder::with_shards(*shards);
+            let calls: Vec<AtomicUsize> = (0..KEYS).map(|_| AtomicUsize::new(0)).collect();
+            let barrier = Barrier::new(THREADS);
+
+            let addresses: Vec<Vec<usize>> = thread::scope(|s| {
+                let threads: Vec<_> = (0..THREADS).map(|t| {
+                    let (holder, calls, barrier) = (&holder, &calls, &barrier);
+                    s.spawn(move || {
+                        barrier.wait();
+                        let mut addresses = vec![0; KEYS];
+                        for i in 0..KEYS {
+                            let key = (i + t * KEYS / THREADS) % KEYS;
+                            let element = holder.get_or_insert_with(&key, || {
+                                calls[key].fetch_add(1, Ordering::Relaxed);
+                                thread::yield_now();
+                                key * 2
+                            });
+                            assert_eq!(*element, key * 2);
+                            addresses[key] = element as *const usize as usize;
+                        }
+                        addresses
+                    })
+                }).collect();
+                threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+            });
+
+            assert_eq!(holder.len(), KEYS);
+            for key in 0..KEYS {
+                assert_eq!(calls[key].load(Ordering::Relaxed), 1, "key {} was initialized more than once", key);
+
+                let address = holder.get(&key).unwrap() as *const usize as usize;
+                assert!(addresses.iter().all(|addresses| addresses[key] == address), "key {} moved", key);
+            }
+        }
+    }
+}
+
+/// References handed out early must stay valid while other threads keep inserting into the same shards.
+#[test]
+fn references_stay_valid_during_concurrent_inserts() {
+    let holder = SyncHolder::with_shards(2);
+    let barrier = Barrier::new(THREADS);
+
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            let (holder, barrier) = (&holder, &barrier);
+            s.spawn(move || {
+                barrier.wait();
+                let first = holder.get_or_insert_with(&format!("{}_0", t), || vec![t; 16]);
+                for i in 1..1000 {
+                    let key = format!("{}_{}", t, i);
+                    holder.insert(&key, vec![t; 16]);
+                    // also race on keys owned by other threads.
+                    holder.get_or_insert_with(&format!("{}_{}", (t + 1) % THREADS, i), || vec![(t + 1) % THREADS; 16]);
+                }
+                assert_eq!(*first, vec![t; 16]);
+            });
+        }
+    });
+
+    assert_eq!(holder.len(), THREADS * 1000);
+    for t in 0..THREADS {
+        assert_eq!(holder.get(&format!("{}_999", t)), Some(&vec![t; 16]));
+    }
+}
+
+/// Inserting a key from inside of its own constructor panics instead of deadlocking, and the key can be inserted later.
+#[test]
+fn reentrant_insert_of_the_same_key_panics() {
+    let holder = SyncHolder::new();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        holder.insert_fn("atlas", || {
+            holder.insert("atlas/left", 1);
+            *holder.get_or_insert_with("atlas", || 2)
+        });
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(holder.get("atlas"), None);
+    assert_eq!(holder.get("atlas/left"), Some(&1));
+    assert_eq!(holder.get_or_insert_with("atlas", || 3), &3);
+}